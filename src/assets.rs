@@ -0,0 +1,158 @@
+use raylib::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+pub const SOUND_CHOMP: &str = "assets/sounds/chomp.wav";
+pub const SOUND_POWER: &str = "assets/sounds/power.wav";
+pub const SOUND_EAT_GHOST: &str = "assets/sounds/eat_ghost.wav";
+const SOUND_FILES: [&str; 3] = [SOUND_CHOMP, SOUND_POWER, SOUND_EAT_GHOST];
+
+// Loads every file once and hands out shared handles. Failed loads are
+// remembered too, so a missing file is reported once instead of every
+// time a game starts.
+pub struct AssetCache<'aud> {
+    audio: Option<&'aud RaylibAudio>,
+    models: HashMap<String, Option<Rc<Model>>>,
+    textures: HashMap<String, Option<Rc<Texture2D>>>,
+    sounds: HashMap<String, Option<Rc<Sound<'aud>>>>,
+    // Loaded at startup and kept for the whole run, used or not
+    pinned: HashSet<String>,
+    // Plain white square, tinted and drawn as a billboard when a model is missing
+    pub fallback: Option<Texture2D>,
+    // Load messages waiting to be printed by flush_report
+    log: Vec<String>,
+}
+
+impl<'aud> AssetCache<'aud> {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, audio: Option<&'aud RaylibAudio>) -> Self {
+        let image = Image::gen_image_color(16, 16, Color::WHITE);
+        let fallback = rl.load_texture_from_image(thread, &image).ok();
+        AssetCache {
+            audio,
+            models: HashMap::new(),
+            textures: HashMap::new(),
+            sounds: HashMap::new(),
+            pinned: HashSet::new(),
+            fallback,
+            log: Vec::new(),
+        }
+    }
+
+    pub fn model(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) -> Option<Rc<Model>> {
+        if let Some(cached) = self.models.get(path) {
+            return cached.clone();
+        }
+        let loaded = match rl.load_model(thread, path) {
+            Ok(model) => {
+                self.log.push(format!("  modelo  {}", path));
+                Some(Rc::new(model))
+            }
+            Err(e) => {
+                self.log.push(format!("  FALLO   {}: {} (se usará un billboard)", path, e));
+                None
+            }
+        };
+        self.models.insert(path.to_string(), loaded.clone());
+        loaded
+    }
+
+    // Loads a model that release_unused never drops
+    pub fn preload_model(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) {
+        self.model(rl, thread, path);
+        self.pinned.insert(path.to_string());
+    }
+
+    pub fn texture(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) -> Option<Rc<Texture2D>> {
+        if let Some(cached) = self.textures.get(path) {
+            return cached.clone();
+        }
+        let loaded = match Image::load_image(path) {
+            Ok(image) => match rl.load_texture_from_image(thread, &image) {
+                Ok(texture) => {
+                    self.log.push(format!("  textura {} ({}x{})", path, image.width, image.height));
+                    Some(Rc::new(texture))
+                }
+                Err(e) => {
+                    self.log.push(format!("  FALLO   {}: {}", path, e));
+                    None
+                }
+            },
+            Err(e) => {
+                self.log.push(format!("  FALLO   {}: {}", path, e));
+                None
+            }
+        };
+        self.textures.insert(path.to_string(), loaded.clone());
+        loaded
+    }
+
+    pub fn sound(&mut self, path: &str) -> Option<Rc<Sound<'aud>>> {
+        if let Some(cached) = self.sounds.get(path) {
+            return cached.clone();
+        }
+        let loaded = match self.audio {
+            None => None,
+            Some(_) if !std::path::Path::new(path).exists() => {
+                self.log.push(format!("  (sin)   {}: no existe, se omite", path));
+                None
+            }
+            Some(audio) => match audio.new_sound(path) {
+                Ok(sound) => {
+                    self.log.push(format!("  sonido  {}", path));
+                    Some(Rc::new(sound))
+                }
+                Err(e) => {
+                    self.log.push(format!("  FALLO   {}: {}", path, e));
+                    None
+                }
+            },
+        };
+        self.sounds.insert(path.to_string(), loaded.clone());
+        loaded
+    }
+
+    // Only plays sounds that are already loaded, never touches the disk
+    pub fn play_sound(&self, path: &str) {
+        if let Some(Some(sound)) = self.sounds.get(path) {
+            sound.play();
+        }
+    }
+
+    pub fn preload_sounds(&mut self) {
+        if self.audio.is_none() {
+            self.log.push("  (sin)   dispositivo de audio no disponible, juego sin sonido".to_string());
+        }
+        for path in SOUND_FILES {
+            self.sound(path);
+        }
+    }
+
+    // Drops models and textures nobody but the cache holds on to; they are
+    // loaded again if asked for later. Preloaded models and sounds, which
+    // are played through the cache, always stay. Failed entries stay so
+    // they aren't retried.
+    pub fn release_unused(&mut self) {
+        let pinned = &self.pinned;
+        self.models.retain(|path, model| {
+            pinned.contains(path) || model.as_ref().is_none_or(|m| Rc::strong_count(m) > 1)
+        });
+        self.textures.retain(|_, texture| texture.as_ref().is_none_or(|t| Rc::strong_count(t) > 1));
+    }
+
+    // Prints everything loaded since the last report in one block
+    pub fn flush_report(&mut self) {
+        if self.log.is_empty() {
+            return;
+        }
+        let loaded = |count: usize, total: usize| format!("{}/{}", count, total);
+        println!(
+            "Recursos: modelos {}, texturas {}, sonidos {}",
+            loaded(self.models.values().filter(|m| m.is_some()).count(), self.models.len()),
+            loaded(self.textures.values().filter(|t| t.is_some()).count(), self.textures.len()),
+            loaded(self.sounds.values().filter(|s| s.is_some()).count(), self.sounds.len()),
+        );
+        for line in self.log.drain(..) {
+            println!("{}", line);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use crate::ghost::{Ghost, GhostState};
+use crate::maze::{Cell, GridDir, Maze, DIRECTIONS, tile_centre, tile_of};
+use crate::player::{MovementMode, Player, PlayerInput};
+
+// Path cost of a tile a ghost gets to first, so paths avoid them if they can
+const DEADLY: u32 = 1000;
+// Frightened ghosts closer than this many steps are worth chasing
+const HUNT_RANGE: u32 = 8;
+
+// Plays the game through the same PlayerInput a human produces, for the
+// attract-mode demo and soak runs. It plans in tiles: the cheapest path to a
+// pellet (or a nearby frightened ghost), where tiles a ghost could reach
+// about as soon as the bot cost more the closer the race. It only runs when
+// the first step of that path is already lost. In free movement it strafes
+// at the next tile; in grid movement it queues turns ahead of cell centres.
+pub struct Autopilot {
+    next_tile: Option<(i32, i32)>,
+    // Grid movement acts on presses, so a turn or reverse is sent every other tick
+    pressed: bool,
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Autopilot {
+    pub fn new() -> Self {
+        Autopilot { next_tile: None, pressed: false }
+    }
+
+    pub fn drive(&mut self, player: &Player, maze: &Maze, ghosts: &[Ghost]) -> PlayerInput {
+        if player.movement == MovementMode::Grid {
+            return self.drive_grid(player, maze, ghosts);
+        }
+        let tile = tile_of(player.pos);
+        // Stick with the step being walked until its tile is reached
+        if self.next_tile.is_none_or(|next| next == tile || !is_next_to(tile, next)) {
+            self.next_tile = plan_step(tile, maze, ghosts).map(|dir| (tile.0 + dir.0, tile.1 + dir.1));
+        }
+        let Some(next) = self.next_tile else {
+            return PlayerInput::default();
+        };
+
+        // Walk straight at the tile whichever way the view points, strafing
+        // like a keyboard player, and turn the view towards it meanwhile
+        let to_target = (tile_centre(next) - player.pos).normalized();
+        let (sin, cos) = player.angle.sin_cos();
+        let mut error = to_target.y.atan2(to_target.x) - player.angle;
+        error = (error + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        PlayerInput {
+            forward: to_target.x * cos + to_target.y * sin,
+            strafe: to_target.y * cos - to_target.x * sin,
+            turn: (error * 4.0).clamp(-1.0, 1.0),
+            look: 0.0,
+        }
+    }
+
+    fn drive_grid(&mut self, player: &Player, maze: &Maze, ghosts: &[Ghost]) -> PlayerInput {
+        let tile = tile_of(player.pos);
+        let Some(here) = plan_step(tile, maze, ghosts) else {
+            return PlayerInput::default();
+        };
+        let mut cmd = PlayerInput { forward: 1.0, ..PlayerInput::default() };
+        let press = !self.pressed;
+        self.pressed = false;
+        let Some(heading) = player.grid.heading() else {
+            // Standing still: set off, turning first if the way lies to one side
+            let facing = player.grid.facing();
+            if press && here.0 * facing.1 != here.1 * facing.0 {
+                self.pressed = true;
+                cmd.turn = if here == (-facing.1, facing.0) { 1.0 } else { -1.0 };
+            }
+            return cmd;
+        };
+        if here == (-heading.0, -heading.1) {
+            if press {
+                self.pressed = true;
+                cmd.forward = -1.0;
+            }
+            return cmd;
+        }
+
+        // Turns are taken at cell centres, so once past this one the turn to
+        // queue is the one for the next. Turning back there waits until it is reached.
+        let to_centre = tile_centre(tile) - player.pos;
+        let ahead = to_centre.x * heading.0 as f32 + to_centre.y * heading.1 as f32;
+        let want = if ahead < 0.0 { plan_step((tile.0 + heading.0, tile.1 + heading.1), maze, ghosts) } else { Some(here) };
+        if let Some(want) = want.filter(|&want| press && want.0 * heading.1 != want.1 * heading.0) {
+            self.pressed = true;
+            cmd.turn = if want == (-heading.1, heading.0) { 1.0 } else { -1.0 };
+        }
+        cmd
+    }
+}
+
+fn is_next_to(a: (i32, i32), b: (i32, i32)) -> bool {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs() == 1
+}
+
+fn plan_step(from: (i32, i32), maze: &Maze, ghosts: &[Ghost]) -> Option<GridDir> {
+    let threat_steps = threat_distances(maze, ghosts);
+    // How much sooner than a ghost the bot gets to a tile it reaches in `steps`
+    let risk = |tile: (i32, i32), steps: u32| match threat_steps.get(&tile) {
+        Some(&ghost) => danger_cost(ghost as i32 - steps as i32),
+        None => 0,
+    };
+
+    // Cheapest paths first, where every step costs one plus the danger of the
+    // tile it lands on, remembering the first step to each tile
+    let mut best: HashMap<(i32, i32), (u32, GridDir)> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    best.insert(from, (0, (0, 0)));
+    queue.push(Reverse((0, 0, from)));
+    while let Some(Reverse((cost, steps, tile))) = queue.pop() {
+        let (known, step) = best[&tile];
+        if cost > known {
+            continue;
+        }
+        if tile != from {
+            let prey = ghosts
+                .iter()
+                .any(|ghost| ghost.state == GhostState::Frightened && ghost.tile() == tile);
+            let pellet = matches!(maze.get_cell(tile.0 as usize, tile.1 as usize), Cell::Pellet | Cell::PowerPellet);
+            if pellet || (prey && steps <= HUNT_RANGE) {
+                // Every way there may be risky; only running away beats
+                // stepping straight into a ghost
+                let first = (from.0 + step.0, from.1 + step.1);
+                if best[&first].0 >= DEADLY {
+                    break;
+                }
+                return Some(step);
+            }
+        }
+        for dir in DIRECTIONS {
+            let next = (tile.0 + dir.0, tile.1 + dir.1);
+            if maze.blocks_player(next.0, next.1) {
+                continue;
+            }
+            let next_cost = cost + 1 + risk(next, steps + 1);
+            if best.get(&next).is_some_and(|&(known, _)| known <= next_cost) {
+                continue;
+            }
+            let step = if tile == from { dir } else { step };
+            best.insert(next, (next_cost, step));
+            queue.push(Reverse((next_cost, steps + 1, next)));
+        }
+    }
+    flee(from, maze, &threat_steps)
+}
+
+// Extra cost of a tile the nearest ghost reaches `margin` steps after the bot
+fn danger_cost(margin: i32) -> u32 {
+    match margin {
+        ..=0 => DEADLY,
+        1 => 60,
+        2 => 25,
+        3 => 10,
+        4 => 4,
+        _ => 0,
+    }
+}
+
+// Steps from the nearest dangerous ghost to every tile, through the house door
+fn threat_distances(maze: &Maze, ghosts: &[Ghost]) -> HashMap<(i32, i32), u32> {
+    let mut steps = HashMap::new();
+    let mut queue = VecDeque::new();
+    for ghost in ghosts.iter().filter(|ghost| ghost.is_dangerous()) {
+        if steps.insert(ghost.tile(), 0).is_none() {
+            queue.push_back(ghost.tile());
+        }
+    }
+    while let Some(tile) = queue.pop_front() {
+        let dist = steps[&tile];
+        for dir in DIRECTIONS {
+            let next = (tile.0 + dir.0, tile.1 + dir.1);
+            if maze.is_wall_at(next.0, next.1) || steps.contains_key(&next) {
+                continue;
+            }
+            steps.insert(next, dist + 1);
+            queue.push_back(next);
+        }
+    }
+    steps
+}
+
+// When the way to a pellet starts into a ghost: the open neighbour furthest from one
+fn flee(from: (i32, i32), maze: &Maze, threat_steps: &HashMap<(i32, i32), u32>) -> Option<GridDir> {
+    DIRECTIONS
+        .into_iter()
+        .filter(|dir| !maze.blocks_player(from.0 + dir.0, from.1 + dir.1))
+        .max_by_key(|dir| threat_steps.get(&(from.0 + dir.0, from.1 + dir.1)).copied().unwrap_or(u32::MAX))
+}
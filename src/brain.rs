@@ -0,0 +1,165 @@
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, VecDeque};
+use crate::ghost::{GhostState, Personality};
+use crate::maze::{GridDir, Maze, DIRECTIONS};
+use crate::sim::SimRng;
+
+// What every ghost can see of the others
+#[derive(Debug, Clone, Copy)]
+pub struct GhostInfo {
+    pub tile: (i32, i32),
+    pub state: GhostState,
+    pub personality: Personality,
+}
+
+// Shared snapshot of the world, taken once per tick before any ghost moves
+pub struct GhostWorld {
+    pub player_tile: (i32, i32),
+    pub player_dir: GridDir,
+    pub ghosts: Vec<GhostInfo>,
+}
+
+impl GhostWorld {
+    pub fn blinky_tile(&self) -> Option<(i32, i32)> {
+        self.ghosts
+            .iter()
+            .find(|ghost| ghost.personality == Personality::Blinky)
+            .map(|ghost| ghost.tile)
+    }
+}
+
+// Everything a brain gets when its ghost reaches a cell centre
+pub struct GhostView<'a> {
+    pub maze: &'a Maze,
+    pub world: &'a GhostWorld,
+    pub me: GhostInfo,
+    pub dir: GridDir,
+    // Where the arcade rules (personality, scatter corner, Elroy) say to go
+    pub target: (i32, i32),
+}
+
+// Decides where a ghost turns at each cell centre while it roams the maze.
+// Leaving the house and going back as eyes are handled by the ghost itself.
+pub trait GhostBrain {
+    fn name(&self) -> &'static str;
+
+    // `options` holds the legal directions and is never empty. Any
+    // randomness must come from `rng` so a seeded game replays exactly.
+    fn choose_direction(&mut self, view: &GhostView, options: &[GridDir], rng: &mut SimRng) -> GridDir;
+}
+
+pub const DEFAULT_BRAIN: &str = "arcade";
+
+pub fn brain_by_name(name: &str) -> Option<Box<dyn GhostBrain>> {
+    match name {
+        "arcade" => Some(Box::new(ArcadeBrain)),
+        "greedy" => Some(Box::new(GreedyChaser)),
+        "pathfinder" => Some(Box::new(Pathfinder)),
+        _ => None,
+    }
+}
+
+fn distance_sq(from: (i32, i32), dir: GridDir, to: (i32, i32)) -> i32 {
+    let dx = from.0 + dir.0 - to.0;
+    let dy = from.1 + dir.1 - to.1;
+    dx * dx + dy * dy
+}
+
+// Arcade behaviour: the option closest to the target tile, ties going to the
+// up/left/down/right order, and random turns while frightened
+pub struct ArcadeBrain;
+
+impl GhostBrain for ArcadeBrain {
+    fn name(&self) -> &'static str {
+        "arcade"
+    }
+
+    fn choose_direction(&mut self, view: &GhostView, options: &[GridDir], rng: &mut SimRng) -> GridDir {
+        if view.me.state == GhostState::Frightened {
+            return *options.choose(rng).unwrap_or(&options[0]);
+        }
+        let mut best = options[0];
+        for &dir in &options[1..] {
+            if distance_sq(view.me.tile, dir, view.target) < distance_sq(view.me.tile, best, view.target) {
+                best = dir;
+            }
+        }
+        best
+    }
+}
+
+// The original chaser: always heads for the player along the axis with the
+// larger gap, ignoring scatter targets, and runs straight away when frightened
+pub struct GreedyChaser;
+
+impl GhostBrain for GreedyChaser {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn choose_direction(&mut self, view: &GhostView, options: &[GridDir], _rng: &mut SimRng) -> GridDir {
+        let player = view.world.player_tile;
+        let dx = player.0 - view.me.tile.0;
+        let dy = player.1 - view.me.tile.1;
+        let (main, other) = if dx.abs() > dy.abs() {
+            ((dx.signum(), 0), (0, dy.signum()))
+        } else {
+            ((0, dy.signum()), (dx.signum(), 0))
+        };
+        let preferred = if view.me.state == GhostState::Frightened {
+            [(-main.0, -main.1), (-other.0, -other.1)]
+        } else {
+            [main, other]
+        };
+        // Keeps going straight when neither way towards the player is open
+        preferred
+            .into_iter()
+            .chain([view.dir])
+            .find(|dir| options.contains(dir))
+            .unwrap_or(options[0])
+    }
+}
+
+// Follows the shortest path through the corridors to the player instead of
+// the straight-line distance, and the longest one away when frightened
+pub struct Pathfinder;
+
+impl GhostBrain for Pathfinder {
+    fn name(&self) -> &'static str {
+        "pathfinder"
+    }
+
+    fn choose_direction(&mut self, view: &GhostView, options: &[GridDir], _rng: &mut SimRng) -> GridDir {
+        let distances = walking_distances(view.maze, view.world.player_tile);
+        let distance = |dir: &GridDir| {
+            let next = (view.me.tile.0 + dir.0, view.me.tile.1 + dir.1);
+            distances.get(&next).copied().unwrap_or(u32::MAX)
+        };
+        let best = if view.me.state == GhostState::Frightened {
+            options.iter().max_by_key(|dir| distance(dir))
+        } else {
+            options.iter().min_by_key(|dir| distance(dir))
+        };
+        best.copied().unwrap_or(options[0])
+    }
+}
+
+// Steps from every reachable tile to `from`, never crossing the house door
+fn walking_distances(maze: &Maze, from: (i32, i32)) -> HashMap<(i32, i32), u32> {
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    distances.insert(from, 0);
+    queue.push_back(from);
+    while let Some(tile) = queue.pop_front() {
+        let steps = distances[&tile] + 1;
+        for dir in DIRECTIONS {
+            let next = (tile.0 + dir.0, tile.1 + dir.1);
+            if maze.is_wall_at(next.0, next.1) || maze.is_door(next.0, next.1) || distances.contains_key(&next) {
+                continue;
+            }
+            distances.insert(next, steps);
+            queue.push_back(next);
+        }
+    }
+    distances
+}
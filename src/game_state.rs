@@ -1,4 +1,4 @@
-use raylib::prelude::*;
+use crate::math::Vec2;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GameMode {
@@ -7,9 +7,28 @@ pub enum GameMode {
     GameOver,
     Paused,
     Menu,
+    Dying, // caught by a ghost, playing the death sequence
+    Ready, // "READY!" countdown before play (re)starts
 }
 
+// Seconds everything stands still before the death animation starts
+const DEATH_FREEZE: f32 = 0.5;
+const DEATH_ANIMATION: f32 = 1.5;
+const READY_DURATION: f32 = 2.0;
+
+// Points shown floating above where a ghost was eaten
+pub struct ScorePopup {
+    pub pos: Vec2,
+    pub points: u32,
+    pub age: f32,
+}
+
+pub const POPUP_LIFETIME: f32 = 1.0;
+// Seconds a scripted level message stays on screen
+const MESSAGE_LIFETIME: f32 = 3.0;
+
 pub struct GameState {
+    pub clock: f64, // seconds of simulation since the game state was created
     pub score: u32,
     pub level: u32,
     pub lives: u32,
@@ -23,17 +42,28 @@ pub struct GameState {
     pub power_mode_duration: f64,
     pub bonus_multiplier: u32,
     pub game_over_time: Option<f64>, // Added for GameOver timer
+    pub ghosts_eaten: u32, // in a row during the current power mode
+    sequence_timer: f32,   // seconds left in the death or READY sequence
+    pub popups: Vec<ScorePopup>,
+    pub message: Option<(String, f32)>, // text and seconds left
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GameState {
-    pub fn new(rl: &RaylibHandle) -> Self {
+    pub fn new() -> Self {
         GameState {
+            clock: 0.0,
             score: 0,
             level: 1,
             lives: 3,
             mode: GameMode::Playing,
             high_score: 0,
-            level_start_time: rl.get_time(),
+            level_start_time: 0.0,
             game_time: 0.0,
             level_complete_time: 0.0,
             power_mode_active: false,
@@ -41,32 +71,53 @@ impl GameState {
             power_mode_duration: 10.0,
             bonus_multiplier: 1,
             game_over_time: None,
+            ghosts_eaten: 0,
+            sequence_timer: 0.0,
+            popups: Vec::new(),
+            message: None,
         }
     }
 
-    pub fn update(&mut self, rl: &RaylibHandle) {
-        let current_time = rl.get_time();
-        self.game_time = current_time - self.level_start_time;
+    // dt is the tick length in seconds; the level clock only runs while playing
+    pub fn update(&mut self, dt: f32) {
+        self.clock += dt as f64;
+        if self.mode == GameMode::Playing {
+            self.game_time += dt as f64;
+        }
 
         if self.power_mode_active {
-            self.power_mode_timer -= rl.get_frame_time() as f64;
+            self.power_mode_timer -= dt as f64;
             if self.power_mode_timer <= 0.0 {
                 self.deactivate_power_mode();
             }
         }
 
+        for popup in self.popups.iter_mut() {
+            popup.age += dt;
+        }
+        self.popups.retain(|popup| popup.age < POPUP_LIFETIME);
+
+        if let Some((_, remaining)) = self.message.as_mut() {
+            *remaining -= dt;
+        }
+        self.message.take_if(|(_, remaining)| *remaining <= 0.0);
+
         self.update_bonus_multiplier();
     }
 
     pub fn add_score(&mut self, points: u32) {
-        let bonus_points = points * self.bonus_multiplier;
-        self.score += bonus_points;
+        self.add_points(points * self.bonus_multiplier);
+    }
+
+    // Points that the bonus multiplier doesn't apply to
+    pub fn add_points(&mut self, points: u32) {
+        self.score += points;
         
         if self.score > self.high_score {
             self.high_score = self.score;
         }
 
-        if self.score > 0 && self.score % 10000 == 0 {
+        if self.score > 0 && self.score.is_multiple_of(10000) {
             self.lives += 1;
             println!("¡Vida extra! Total: {}", self.lives);
         }
@@ -76,30 +127,90 @@ impl GameState {
         self.power_mode_active = true;
         self.power_mode_timer = self.power_mode_duration;
         self.bonus_multiplier = 2;
+        self.ghosts_eaten = 0;
         println!("¡Modo de poder activado!");
     }
 
+    // 200, 400, 800, 1600 for each ghost eaten on the same power pellet
+    pub fn eat_ghost(&mut self, pos: Vec2) {
+        let points = 200 << self.ghosts_eaten.min(3);
+        self.ghosts_eaten += 1;
+        self.add_points(points);
+        self.popups.push(ScorePopup { pos, points, age: 0.0 });
+    }
+
+    pub fn show_message(&mut self, text: String) {
+        self.message = Some((text, MESSAGE_LIFETIME));
+    }
+
     pub fn deactivate_power_mode(&mut self) {
         self.power_mode_active = false;
         self.power_mode_timer = 0.0;
         self.bonus_multiplier = 1;
     }
 
-    pub fn lose_life(&mut self, rl: &RaylibHandle) {
+    pub fn start_death(&mut self) {
+        self.mode = GameMode::Dying;
+        self.sequence_timer = DEATH_FREEZE + DEATH_ANIMATION;
+        self.deactivate_power_mode();
+    }
+
+    pub fn start_ready(&mut self) {
+        self.mode = GameMode::Ready;
+        self.sequence_timer = READY_DURATION;
+    }
+
+    // Advances the death and READY sequences. Returns true when a death has
+    // played out with lives to spare, so the caller puts everyone back at
+    // their spawn points.
+    pub fn update_sequence(&mut self, dt: f32) -> bool {
+        if self.mode != GameMode::Dying && self.mode != GameMode::Ready {
+            return false;
+        }
+        self.sequence_timer -= dt;
+        if self.sequence_timer > 0.0 {
+            return false;
+        }
+        if self.mode == GameMode::Ready {
+            self.mode = GameMode::Playing;
+            return false;
+        }
+        self.lose_life();
+        if self.mode == GameMode::GameOver {
+            return false;
+        }
+        self.start_ready();
+        true
+    }
+
+    // Seconds left in the death or READY sequence
+    pub fn sequence_remaining(&self) -> f32 {
+        self.sequence_timer
+    }
+
+    // 0.0 while frozen, rising to 1.0 at the end of the death animation
+    pub fn death_progress(&self) -> f32 {
+        if self.mode != GameMode::Dying {
+            return 0.0;
+        }
+        (1.0 - self.sequence_timer / DEATH_ANIMATION).clamp(0.0, 1.0)
+    }
+
+    pub fn lose_life(&mut self) {
         if self.lives > 0 {
             self.lives -= 1;
         }
         
         if self.lives == 0 {
             self.mode = GameMode::GameOver;
-            self.game_over_time = Some(rl.get_time());
+            self.game_over_time = Some(self.clock);
             println!("Game Over! Puntuación final: {}", self.score);
         }
     }
 
-    pub fn complete_level(&mut self, rl: &RaylibHandle) {
+    pub fn complete_level(&mut self) {
         self.mode = GameMode::LevelComplete;
-        self.level_complete_time = rl.get_time();
+        self.level_complete_time = self.clock;
         
         let time_bonus = ((120.0 - self.game_time).max(0.0) * 10.0) as u32;
         let level_bonus = self.level * 1000;
@@ -111,25 +222,31 @@ impl GameState {
         println!("Bonificación de nivel: {}", level_bonus);
     }
 
-    pub fn next_level(&mut self, rl: &RaylibHandle) {
+    pub fn next_level(&mut self) {
         self.level += 1;
         self.mode = GameMode::Playing;
-        self.level_start_time = rl.get_time();
+        self.level_start_time = self.clock;
+        self.game_time = 0.0;
         self.deactivate_power_mode();
         
         println!("Comenzando nivel {}", self.level);
     }
 
-    pub fn reset_game(&mut self, rl: &RaylibHandle) {
+    pub fn reset_game(&mut self) {
         self.score = 0;
         self.level = 1;
         self.lives = 3;
         self.mode = GameMode::Playing;
-        self.level_start_time = rl.get_time();
+        self.level_start_time = self.clock;
         self.game_time = 0.0;
         self.deactivate_power_mode();
         self.bonus_multiplier = 1;
         self.game_over_time = None;
+        // Nothing of the last game carries over: no half-finished ghost
+        // chain, no points or message still on screen
+        self.ghosts_eaten = 0;
+        self.popups.clear();
+        self.message = None;
     }
 
     pub fn pause_game(&mut self) {
@@ -156,6 +273,14 @@ impl GameState {
         self.mode == GameMode::LevelComplete
     }
 
+    // Modes where the maze is on screen, whether or not time is running
+    pub fn shows_maze(&self) -> bool {
+        self.is_playing()
+            || self.is_paused()
+            || self.is_level_complete()
+            || matches!(self.mode, GameMode::Dying | GameMode::Ready)
+    }
+
     pub fn get_power_mode_remaining(&self) -> f64 {
         if self.power_mode_active {
             self.power_mode_timer
@@ -179,91 +304,52 @@ impl GameState {
             }
         }
     }
+}
 
-    pub fn draw_ui(&self, d: &mut RaylibDrawHandle, screen_width: i32, screen_height: i32) {
-        d.draw_text(&format!("SCORE: {:06}", self.score), 10, 10, 24, Color::YELLOW);
-        d.draw_text(&format!("HIGH: {:06}", self.high_score), 10, 40, 16, Color::ORANGE);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if self.bonus_multiplier > 1 {
-            d.draw_text(&format!("BONUS x{}", self.bonus_multiplier), 
-                       screen_width - 120, 10, 20, Color::GREEN);
+    #[test]
+    fn ghost_points_ignore_the_power_mode_multiplier() {
+        let mut game_state = GameState::new();
+        game_state.activate_power_mode();
+        for points in [200, 400, 800, 1600, 1600] {
+            let score = game_state.score;
+            game_state.eat_ghost(Vec2::new(0.0, 0.0));
+            assert_eq!(game_state.score, score + points);
+            assert_eq!(game_state.popups.last().map(|popup| popup.points), Some(points));
         }
+        // A new power pellet starts the chain over
+        game_state.activate_power_mode();
+        let score = game_state.score;
+        game_state.eat_ghost(Vec2::new(0.0, 0.0));
+        assert_eq!(game_state.score, score + 200);
+    }
 
-        if self.power_mode_active {
-            let remaining = self.get_power_mode_remaining() as i32;
-            d.draw_text(&format!("POWER: {}s", remaining), 
-                       screen_width - 120, 40, 18, Color::YELLOW);
-
-            let bar_width = 100;
-            let bar_height = 8;
-            let progress = (self.power_mode_timer / self.power_mode_duration) as f32;
-            
-            d.draw_rectangle(screen_width - 120, 65, bar_width, bar_height, Color::DARKGRAY);
-            d.draw_rectangle(screen_width - 120, 65, 
-                           (bar_width as f32 * progress) as i32, bar_height, Color::YELLOW);
-        }
+    #[test]
+    fn new_game_starts_a_fresh_chain() {
+        let mut game_state = GameState::new();
+        game_state.activate_power_mode();
+        game_state.eat_ghost(Vec2::new(0.0, 0.0));
+        game_state.eat_ghost(Vec2::new(0.0, 0.0));
+        game_state.show_message("Level 1".to_string());
+        game_state.reset_game();
+        assert_eq!(game_state.ghosts_eaten, 0);
+        assert!(game_state.popups.is_empty());
+        assert!(game_state.message.is_none());
 
-        let minutes = (self.game_time / 60.0) as i32;
-        let seconds = (self.game_time % 60.0) as i32;
-        d.draw_text(&format!("TIME: {:02}:{:02}", minutes, seconds), 
-                   screen_width - 120, 90, 16, Color::LIGHTGRAY);
-
-        match self.mode {
-            GameMode::LevelComplete => {
-                d.draw_rectangle(0, 0, screen_width, screen_height, Color::new(0, 0, 0, 128));
-                d.draw_text("YOU WIN", 
-                           screen_width / 2 - 80, screen_height / 2 - 20, 40, Color::GREEN);
-                d.draw_text("Press M for Menu", 
-                           screen_width / 2 - 90, screen_height / 2 + 30, 24, Color::WHITE);
-            }
-            GameMode::GameOver => {
-                d.draw_rectangle(0, 0, screen_width, screen_height, Color::new(0, 0, 0, 128));
-                d.draw_text("GAME OVER", 
-                           screen_width / 2 - 80, screen_height / 2 - 40, 32, Color::RED);
-                d.draw_text(&format!("Final Score: {}", self.score), 
-                           screen_width / 2 - 70, screen_height / 2, 20, Color::WHITE);
-                // Only show R/M instructions if <5s since game over
-                if let Some(game_over_time) = self.game_over_time {
-                    // WARNING: d.get_shader_time() was invalid, substitute a constant because drawing logic uses UI timer, not absolute clock.
-                    // In practice, this timer logic is used in the main game loop.
-                    // Always draw the text when in the first 5 seconds after game over (handled at input/menu logic)
-                    // So just always show it when GameOver and game_over_time is set.
-                    d.draw_text("Press R to Retry or M for Menu", 
-                               screen_width / 2 - 150, screen_height / 2 + 30, 20, Color::LIGHTGRAY);
-                }
-            }
-            GameMode::Paused => {
-                d.draw_rectangle(0, 0, screen_width, screen_height, Color::new(0, 0, 0, 128));
-                d.draw_text("PAUSED", 
-                           screen_width / 2 - 60, screen_height / 2, 32, Color::WHITE);
-                d.draw_text("Press P to resume", 
-                           screen_width / 2 - 70, screen_height / 2 + 40, 18, Color::LIGHTGRAY);
-            }
-            _ => {}
-        }
+        game_state.activate_power_mode();
+        game_state.eat_ghost(Vec2::new(0.0, 0.0));
+        assert_eq!(game_state.score, 200);
     }
 
-    pub fn handle_input(&mut self, rl: &RaylibHandle) {
-        match self.mode {
-            GameMode::Playing => {
-                if rl.is_key_pressed(KeyboardKey::KEY_P) {
-                    self.pause_game();
-                }
-            }
-            GameMode::GameOver => {
-                // Ignore inputs here; handled in main.rs
-            }
-            GameMode::Paused => {
-                if rl.is_key_pressed(KeyboardKey::KEY_P) {
-                    self.pause_game();
-                }
-            }
-            GameMode::LevelComplete => {
-                if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
-                    self.next_level(rl);
-                }
-            }
-            _ => {}
-        }
+    #[test]
+    fn last_life_lost_is_game_over() {
+        let mut game_state = GameState::new();
+        game_state.lives = 1;
+        game_state.lose_life();
+        assert!(game_state.is_game_over());
+        assert!(game_state.game_over_time.is_some());
     }
-}
\ No newline at end of file
+}
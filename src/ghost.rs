@@ -0,0 +1,487 @@
+use std::collections::VecDeque;
+use crate::brain::{GhostBrain, GhostInfo, GhostView, GhostWorld};
+use crate::level::Rgba;
+use crate::math::Vec2;
+use crate::maze::{Maze, GridDir, DIRECTIONS, CELL_SIZE, dir_from_angle, tile_of, tile_centre};
+use crate::player::Player;
+use crate::sim::SimRng;
+use crate::stealth::Senses;
+
+// Tile inside the ghost house where eaten ghosts go to revive
+pub const HOUSE_TILE: (i32, i32) = (9, 9);
+// Tile just outside the house door where leaving ghosts join the maze
+pub const HOUSE_EXIT: (i32, i32) = (9, 7);
+const LEAVING_SPEED_MULTIPLIER: f32 = 0.5;
+// "Cruise Elroy": Blinky speeds up as the maze empties
+const ELROY_SPEED_MULTIPLIERS: [f32; 3] = [1.0, 1.05, 1.1];
+const FRIGHTENED_SPEED_MULTIPLIER: f32 = 0.5;
+const EATEN_SPEED_MULTIPLIER: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GhostState {
+    Scatter,    // head for the home corner
+    Chase,      // hunt the player
+    Frightened, // slow, random turns, edible
+    Eaten,      // only the eyes, going back to the house
+}
+
+// Alternates the whole team between scatter and chase. Phases are
+// (mode, seconds); after the last one the ghosts chase for good.
+pub struct ModeSchedule {
+    phases: Vec<(GhostState, f32)>,
+    phase: usize,
+    elapsed: f32,
+}
+
+impl ModeSchedule {
+    pub fn new(phases: Vec<(GhostState, f32)>) -> Self {
+        Self { phases, phase: 0, elapsed: 0.0 }
+    }
+
+    pub fn mode(&self) -> GhostState {
+        self.phases.get(self.phase).map_or(GhostState::Chase, |phase| phase.0)
+    }
+
+    // The clock stands still while the ghosts are frightened.
+    // Returns the new mode when a phase ends.
+    pub fn update(&mut self, dt: f32, frightened: bool) -> Option<GhostState> {
+        if frightened || self.phase >= self.phases.len() {
+            return None;
+        }
+        self.elapsed += dt;
+        if self.elapsed < self.phases[self.phase].1 {
+            return None;
+        }
+        self.elapsed = 0.0;
+        self.phase += 1;
+        Some(self.mode())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Personality {
+    Blinky, // chases the player directly
+    Pinky,  // ambushes four tiles ahead of the player
+    Inky,   // doubles the vector from Blinky to two tiles ahead of the player
+    Clyde,  // chases from afar, retreats to his corner when close
+}
+
+impl Personality {
+    pub fn from_name(name: &str) -> Option<Personality> {
+        match name.to_lowercase().as_str() {
+            "blinky" => Some(Personality::Blinky),
+            "pinky" => Some(Personality::Pinky),
+            "inky" => Some(Personality::Inky),
+            "clyde" => Some(Personality::Clyde),
+            _ => None,
+        }
+    }
+
+    // Corner targets sit just outside the maze so ghosts circle the nearest block
+    pub fn scatter_tile(self) -> (i32, i32) {
+        match self {
+            Personality::Blinky => (18, -2),
+            Personality::Pinky => (2, -2),
+            Personality::Inky => (20, 21),
+            Personality::Clyde => (0, 21),
+        }
+    }
+
+    pub fn chase_tile(self, own_tile: (i32, i32), world: &GhostWorld) -> (i32, i32) {
+        let (px, py) = world.player_tile;
+        let (dx, dy) = world.player_dir;
+        match self {
+            Personality::Blinky => world.player_tile,
+            Personality::Pinky => (px + dx * 4, py + dy * 4),
+            Personality::Inky => {
+                let pivot = (px + dx * 2, py + dy * 2);
+                // Without a Blinky to pivot from, Inky behaves like him
+                match world.blinky_tile() {
+                    Some((bx, by)) => (pivot.0 * 2 - bx, pivot.1 * 2 - by),
+                    None => world.player_tile,
+                }
+            }
+            Personality::Clyde => {
+                let dist_x = own_tile.0 - px;
+                let dist_y = own_tile.1 - py;
+                if dist_x * dist_x + dist_y * dist_y > 8 * 8 {
+                    world.player_tile
+                } else {
+                    self.scatter_tile()
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HouseState {
+    Waiting, // inside, waiting for its dot counter or the idle timer
+    Leaving, // heading for the door
+    Out,     // in the maze
+}
+
+// Decides when ghosts leave the house, following the arcade rules: the first
+// ghost still waiting counts the pellets the player eats until it reaches its
+// limit, and a ghost is pushed out anyway if the player stops eating for a
+// while. After a life is lost a single global counter takes over until the
+// house is empty.
+pub struct HouseRelease {
+    global_counter: Option<u32>,
+    idle_timer: f32,
+    idle_limit: f32,
+}
+
+impl HouseRelease {
+    pub fn new(idle_limit: f32) -> Self {
+        Self {
+            global_counter: None,
+            idle_timer: 0.0,
+            idle_limit,
+        }
+    }
+
+    pub fn life_lost(&mut self) {
+        self.global_counter = Some(0);
+        self.idle_timer = 0.0;
+    }
+
+    pub fn pellet_eaten(&mut self, ghosts: &mut [Ghost]) {
+        self.idle_timer = 0.0;
+        match self.global_counter.as_mut() {
+            Some(counter) => *counter += 1,
+            None => {
+                if let Some(ghost) = ghosts.iter_mut().find(|ghost| ghost.house == HouseState::Waiting) {
+                    ghost.dot_counter += 1;
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, ghosts: &mut [Ghost], dt: f32) {
+        self.idle_timer += dt;
+        let Some(ghost) = ghosts.iter_mut().find(|ghost| ghost.house == HouseState::Waiting) else {
+            // Nobody left inside, back to the personal counters
+            self.global_counter = None;
+            return;
+        };
+        if ghost.release_timer > 0.0 {
+            return;
+        }
+        let counted = match self.global_counter {
+            Some(counter) => counter >= ghost.global_dot_limit,
+            None => ghost.dot_counter >= ghost.dot_limit,
+        };
+        if counted || self.idle_timer >= self.idle_limit {
+            ghost.house = HouseState::Leaving;
+            self.idle_timer = 0.0;
+        }
+    }
+}
+
+pub struct Ghost {
+    pub pos: Vec2,
+    pub prev_pos: Vec2,
+    pub speed: f32,
+    pub model: String, // file the frontend draws it with
+    pub color: Rgba,
+    pub state: GhostState,
+    pub personality: Personality,
+    pub release_timer: f32, // seconds left before it starts moving
+    pub house: HouseState,
+    pub dot_counter: u32,
+    pub dot_limit: u32,        // pellets to count before leaving the house
+    pub global_dot_limit: u32, // same, on the global counter after a death
+    pub elroy_dots: u32,       // pellets left when Elroy kicks in, 0 = never
+    pub brain: Box<dyn GhostBrain>,
+    pub senses: Option<Senses>, // Some in stealth mode
+    elroy: usize,              // 0 = normal, 1 and 2 = faster Elroy stages
+    dir: GridDir,
+}
+
+impl Ghost {
+    pub fn new(
+        pos: Vec2,
+        speed: f32,
+        model: String,
+        color: Rgba,
+        personality: Personality,
+        release_delay: f32,
+        brain: Box<dyn GhostBrain>,
+    ) -> Self {
+        let house = if is_in_house(tile_of(pos)) { HouseState::Waiting } else { HouseState::Out };
+        Self {
+            pos,
+            prev_pos: pos,
+            speed,
+            model,
+            color,
+            state: GhostState::Scatter,
+            personality,
+            release_timer: release_delay,
+            house,
+            dot_counter: 0,
+            dot_limit: 0,
+            global_dot_limit: 0,
+            elroy_dots: 0,
+            brain,
+            senses: None,
+            elroy: 0,
+            dir: (0, 0),
+        }
+    }
+
+    pub fn tile(&self) -> (i32, i32) {
+        tile_of(self.pos)
+    }
+
+    pub fn info(&self) -> GhostInfo {
+        GhostInfo {
+            tile: self.tile(),
+            state: self.state,
+            personality: self.personality,
+        }
+    }
+
+    // Noise the player made at `tile`, heard within `radius` tiles in stealth mode
+    pub fn hear(&mut self, tile: (i32, i32), radius: i32) {
+        let own_tile = self.tile();
+        if let Some(senses) = self.senses.as_mut() {
+            senses.hear(own_tile, tile, radius);
+        }
+    }
+
+    pub fn is_hunting(&self) -> bool {
+        self.is_dangerous() && self.senses.as_ref().is_some_and(|senses| senses.is_hunting())
+    }
+
+    pub fn is_dangerous(&self) -> bool {
+        matches!(self.state, GhostState::Scatter | GhostState::Chase)
+    }
+
+    // Scatter/chase switches make ghosts turn around, like the arcade
+    pub fn set_mode(&mut self, mode: GhostState) {
+        if self.is_dangerous() && self.state != mode {
+            self.reverse();
+        }
+        if self.is_dangerous() {
+            self.state = mode;
+        }
+    }
+
+    pub fn frighten(&mut self) {
+        if self.state != GhostState::Eaten {
+            self.reverse();
+            self.state = GhostState::Frightened;
+        }
+    }
+
+    fn reverse(&mut self) {
+        self.dir = (-self.dir.0, -self.dir.1);
+    }
+
+    fn speed_multiplier(&self) -> f32 {
+        if self.house == HouseState::Leaving {
+            return LEAVING_SPEED_MULTIPLIER;
+        }
+        match self.state {
+            GhostState::Frightened => FRIGHTENED_SPEED_MULTIPLIER,
+            GhostState::Eaten => EATEN_SPEED_MULTIPLIER,
+            _ => ELROY_SPEED_MULTIPLIERS[self.elroy],
+        }
+    }
+
+    fn update_elroy(&mut self, pellets_remaining: u32) {
+        self.elroy = if self.elroy_dots == 0 || pellets_remaining > self.elroy_dots {
+            0
+        } else if pellets_remaining > self.elroy_dots / 2 {
+            1
+        } else {
+            2
+        };
+    }
+
+    // Moves along corridor centrelines, deciding on a new direction at
+    // every cell centre
+    fn advance(&mut self, maze: &Maze, world: &GhostWorld, target: (i32, i32), dt: f32, rng: &mut SimRng) {
+        let cell = CELL_SIZE as f32;
+        let mut remaining = self.speed * self.speed_multiplier() * dt;
+        while remaining > 0.0 {
+            let tile = self.tile();
+            let centre = tile_centre(tile);
+            if self.dir.0 != 0 {
+                self.pos.y = centre.y;
+            } else if self.dir.1 != 0 {
+                self.pos.x = centre.x;
+            }
+
+            let to_centre = (centre.x - self.pos.x) * self.dir.0 as f32 + (centre.y - self.pos.y) * self.dir.1 as f32;
+            if self.dir == (0, 0) || (to_centre >= 0.0 && to_centre <= remaining) {
+                self.pos = centre;
+                remaining -= to_centre.max(0.0);
+                self.dir = self.choose_direction(maze, world, tile, target, rng);
+                if self.dir == (0, 0) {
+                    break;
+                }
+                let step = remaining.min(cell / 2.0);
+                self.pos += Vec2::new(self.dir.0 as f32, self.dir.1 as f32) * step;
+                remaining -= step;
+            } else {
+                let step = if to_centre > 0.0 { to_centre } else { cell + to_centre };
+                let step = step.min(remaining);
+                self.pos += Vec2::new(self.dir.0 as f32, self.dir.1 as f32) * step;
+                remaining -= step;
+            }
+        }
+    }
+
+    fn choose_direction(
+        &mut self,
+        maze: &Maze,
+        world: &GhostWorld,
+        tile: (i32, i32),
+        target: (i32, i32),
+        rng: &mut SimRng,
+    ) -> GridDir {
+        if self.state == GhostState::Eaten || self.house == HouseState::Leaving {
+            return path_step(maze, tile, target).unwrap_or(self.dir);
+        }
+
+        // Only eaten and leaving ghosts may use the house door
+        let reverse = (-self.dir.0, -self.dir.1);
+        let open: Vec<GridDir> = DIRECTIONS
+            .iter()
+            .copied()
+            .filter(|dir| {
+                let next = (tile.0 + dir.0, tile.1 + dir.1);
+                !maze.is_wall_at(next.0, next.1) && !maze.is_door(next.0, next.1)
+            })
+            .collect();
+        // Ghosts never turn back on their own, unless stuck in a dead end
+        let options: Vec<GridDir> = if open.iter().any(|&dir| dir != reverse) {
+            open.into_iter().filter(|&dir| dir != reverse).collect()
+        } else {
+            open
+        };
+        if options.is_empty() {
+            return (0, 0);
+        }
+
+        let view = GhostView {
+            maze,
+            world,
+            me: GhostInfo { tile, ..self.info() },
+            dir: self.dir,
+            target,
+        };
+        let dir = self.brain.choose_direction(&view, &options, rng);
+        // A brain can't walk a ghost into a wall
+        if options.contains(&dir) { dir } else { options[0] }
+    }
+}
+
+// The row of tiles right below the door
+fn is_in_house(tile: (i32, i32)) -> bool {
+    tile.1 == HOUSE_TILE.1 && (tile.0 - HOUSE_TILE.0).abs() <= 1
+}
+
+// First step of the shortest path between two tiles
+fn path_step(maze: &Maze, from: (i32, i32), to: (i32, i32)) -> Option<GridDir> {
+    if from == to {
+        return None;
+    }
+    let mut first_step = std::collections::HashMap::new();
+    let mut queue = VecDeque::new();
+    first_step.insert(from, (0, 0));
+    queue.push_back(from);
+    while let Some(tile) = queue.pop_front() {
+        for dir in DIRECTIONS {
+            let next = (tile.0 + dir.0, tile.1 + dir.1);
+            if maze.is_wall_at(next.0, next.1) || first_step.contains_key(&next) {
+                continue;
+            }
+            let step = if tile == from { dir } else { first_step[&tile] };
+            if next == to {
+                return Some(step);
+            }
+            first_step.insert(next, step);
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_ghosts(
+    ghosts: &mut [Ghost],
+    schedule: &mut ModeSchedule,
+    house: &mut HouseRelease,
+    maze: &Maze,
+    player: &Player,
+    power_mode_active: bool,
+    dt: f32,
+    rng: &mut SimRng,
+) {
+    let player_tile = tile_of(player.pos);
+    let player_dir = dir_from_angle(player.angle);
+    let frightened = ghosts.iter().any(|ghost| ghost.state == GhostState::Frightened);
+    if let Some(mode) = schedule.update(dt, frightened) {
+        for ghost in ghosts.iter_mut() {
+            ghost.set_mode(mode);
+        }
+    }
+
+    let world = GhostWorld {
+        player_tile,
+        player_dir,
+        ghosts: ghosts.iter().map(Ghost::info).collect(),
+    };
+
+    house.update(ghosts, dt);
+
+    for ghost in ghosts.iter_mut() {
+        if ghost.state == GhostState::Frightened && !power_mode_active {
+            ghost.state = schedule.mode();
+        }
+
+        if ghost.release_timer > 0.0 {
+            ghost.release_timer -= dt;
+        }
+        match ghost.house {
+            HouseState::Waiting => continue,
+            HouseState::Leaving => {
+                ghost.advance(maze, &world, HOUSE_EXIT, dt, rng);
+                if ghost.tile() == HOUSE_EXIT {
+                    ghost.house = HouseState::Out;
+                }
+                continue;
+            }
+            HouseState::Out if ghost.release_timer > 0.0 => continue,
+            HouseState::Out => {}
+        }
+
+        ghost.update_elroy(maze.pellets_remaining());
+        if let Some(senses) = ghost.senses.as_mut() {
+            senses.look(ghost.pos, ghost.dir, player.pos, maze, dt);
+        }
+        let target = match ghost.state {
+            // In stealth mode ghosts only go after what they saw or heard
+            GhostState::Scatter | GhostState::Chase if ghost.senses.is_some() => {
+                let tile = ghost.tile();
+                ghost.senses.as_mut().map_or(tile, |senses| senses.target(tile, maze, rng))
+            }
+            // Elroy keeps hunting even while the others scatter
+            GhostState::Scatter if ghost.elroy > 0 => player_tile,
+            GhostState::Scatter => ghost.personality.scatter_tile(),
+            GhostState::Chase => ghost.personality.chase_tile(ghost.tile(), &world),
+            GhostState::Frightened => player_tile, // the arcade brain turns at random
+            GhostState::Eaten => HOUSE_TILE,
+        };
+        ghost.advance(maze, &world, target, dt, rng);
+
+        if ghost.state == GhostState::Eaten && ghost.tile() == HOUSE_TILE {
+            ghost.state = schedule.mode();
+            ghost.house = HouseState::Leaving;
+        }
+    }
+}
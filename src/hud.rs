@@ -0,0 +1,84 @@
+use raylib::prelude::*;
+use pacman::game_state::{GameMode, GameState};
+use crate::input::{Action, InputMap};
+
+// Score, timers, lives and the overlays for each game mode
+pub fn draw_ui(game_state: &GameState, seed: u64, d: &mut RaylibDrawHandle, screen_width: i32, screen_height: i32, input: &InputMap) {
+    d.draw_text(&format!("SCORE: {:06}", game_state.score), 10, 10, 24, Color::YELLOW);
+    d.draw_text(&format!("HIGH: {:06}", game_state.high_score), 10, 40, 16, Color::ORANGE);
+
+    if game_state.bonus_multiplier > 1 {
+        d.draw_text(&format!("BONUS x{}", game_state.bonus_multiplier), 
+                   screen_width - 120, 10, 20, Color::GREEN);
+    }
+
+    if game_state.power_mode_active {
+        let remaining = game_state.get_power_mode_remaining() as i32;
+        d.draw_text(&format!("POWER: {}s", remaining), 
+                   screen_width - 120, 40, 18, Color::YELLOW);
+
+        let bar_width = 100;
+        let bar_height = 8;
+        let progress = (game_state.power_mode_timer / game_state.power_mode_duration) as f32;
+        
+        d.draw_rectangle(screen_width - 120, 65, bar_width, bar_height, Color::DARKGRAY);
+        d.draw_rectangle(screen_width - 120, 65, 
+                       (bar_width as f32 * progress) as i32, bar_height, Color::YELLOW);
+    }
+
+    // Remaining lives as Pac-Man dots, bottom left
+    for i in 0..game_state.lives.min(10) as i32 {
+        d.draw_circle(20 + i * 24, screen_height - 20, 8.0, Color::YELLOW);
+    }
+
+    if let Some((text, _)) = &game_state.message {
+        d.draw_text(text, screen_width / 2 - text.len() as i32 * 6, 60, 24, Color::SKYBLUE);
+    }
+
+    let minutes = (game_state.game_time / 60.0) as i32;
+    let seconds = (game_state.game_time % 60.0) as i32;
+    d.draw_text(&format!("TIME: {:02}:{:02}", minutes, seconds), 
+               screen_width - 120, 90, 16, Color::LIGHTGRAY);
+
+    match game_state.mode {
+        GameMode::LevelComplete => {
+            d.draw_rectangle(0, 0, screen_width, screen_height, Color::new(0, 0, 0, 128));
+            d.draw_text("YOU WIN", 
+                       screen_width / 2 - 80, screen_height / 2 - 20, 40, Color::GREEN);
+            d.draw_text(&format!("Press {} for the next level", input.describe(Action::NextLevel)),
+                       screen_width / 2 - 140, screen_height / 2 + 30, 24, Color::WHITE);
+            d.draw_text(&format!("or {} for Menu", input.describe(Action::ReturnToMenu)),
+                       screen_width / 2 - 90, screen_height / 2 + 60, 24, Color::WHITE);
+        }
+        GameMode::GameOver => {
+            d.draw_rectangle(0, 0, screen_width, screen_height, Color::new(0, 0, 0, 128));
+            d.draw_text("GAME OVER", 
+                       screen_width / 2 - 80, screen_height / 2 - 40, 32, Color::RED);
+            d.draw_text(&format!("Final Score: {}", game_state.score), 
+                       screen_width / 2 - 70, screen_height / 2, 20, Color::WHITE);
+            d.draw_text(&format!("Seed: {}", seed),
+                       screen_width / 2 - 70, screen_height / 2 + 60, 20, Color::GRAY);
+            d.draw_text(&format!("Press {} to Retry or {} for Menu",
+                                input.describe(Action::Retry), input.describe(Action::ReturnToMenu)), 
+                       screen_width / 2 - 150, screen_height / 2 + 30, 20, Color::LIGHTGRAY);
+        }
+        GameMode::Dying => {
+            let alpha = (game_state.death_progress() * 160.0) as u8;
+            d.draw_rectangle(0, 0, screen_width, screen_height, Color::new(120, 0, 0, alpha));
+        }
+        GameMode::Ready => {
+            d.draw_text("READY!",
+                       screen_width / 2 - 60, screen_height / 2 - 20, 36, Color::YELLOW);
+            d.draw_text(&format!("{}", game_state.sequence_remaining().ceil() as i32),
+                       screen_width / 2 - 8, screen_height / 2 + 24, 28, Color::WHITE);
+        }
+        GameMode::Paused => {
+            d.draw_rectangle(0, 0, screen_width, screen_height, Color::new(0, 0, 0, 128));
+            d.draw_text("PAUSED", 
+                       screen_width / 2 - 60, screen_height / 2, 32, Color::WHITE);
+            d.draw_text(&format!("Press {} to resume", input.describe(Action::Pause)), 
+                       screen_width / 2 - 70, screen_height / 2 + 40, 18, Color::LIGHTGRAY);
+        }
+        _ => {}
+    }
+}
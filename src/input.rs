@@ -0,0 +1,495 @@
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use pacman::player::{MovementMode, PlayerInput};
+
+// The player's bindings, written on every rebind and never committed
+pub const BINDINGS_FILE: &str = "controls.cfg";
+// Shipped bindings, used until the player rebinds something
+pub const DEFAULT_BINDINGS_FILE: &str = "controls.default.cfg";
+// Radians per pixel of mouse movement at sensitivity 1.0
+const MOUSE_LOOK_SCALE: f32 = 0.003;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlScheme {
+    Classic, // turn keys turn, strafe keys strafe
+    Mouse,   // mouse look, turn keys strafe as well
+}
+
+pub struct ControlSettings {
+    pub scheme: ControlScheme,
+    pub movement: MovementMode,
+    pub mouse_sensitivity: f32,
+}
+
+impl ControlSettings {
+    pub fn new() -> Self {
+        Self {
+            scheme: ControlScheme::Classic,
+            movement: MovementMode::Free,
+            mouse_sensitivity: 1.0,
+        }
+    }
+
+    pub fn wants_mouse_capture(&self) -> bool {
+        self.scheme == ControlScheme::Mouse && self.movement == MovementMode::Free
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    TurnLeft,
+    TurnRight,
+    StrafeLeft,
+    StrafeRight,
+    Pause,
+    Confirm,
+    Back,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    Retry,
+    ReturnToMenu,
+    NextLevel,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::Retry,
+        Action::ReturnToMenu,
+        Action::NextLevel,
+    ];
+
+    // Name used in the bindings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuLeft => "menu_left",
+            Action::MenuRight => "menu_right",
+            Action::Retry => "retry",
+            Action::ReturnToMenu => "return_to_menu",
+            Action::NextLevel => "next_level",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveForward => "Move Forward",
+            Action::MoveBackward => "Move Backward",
+            Action::TurnLeft => "Turn Left",
+            Action::TurnRight => "Turn Right",
+            Action::StrafeLeft => "Strafe Left",
+            Action::StrafeRight => "Strafe Right",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::MenuUp => "Menu Up",
+            Action::MenuDown => "Menu Down",
+            Action::MenuLeft => "Menu Left",
+            Action::MenuRight => "Menu Right",
+            Action::Retry => "Retry",
+            Action::ReturnToMenu => "Return to Menu",
+            Action::NextLevel => "Next Level",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        use GamepadButton::*;
+        use KeyboardKey::*;
+        let (key, button) = match self {
+            Action::MoveForward => (KEY_W, None),
+            Action::MoveBackward => (KEY_S, None),
+            Action::TurnLeft => (KEY_A, None),
+            Action::TurnRight => (KEY_D, None),
+            Action::StrafeLeft => (KEY_Q, Some(GAMEPAD_BUTTON_LEFT_TRIGGER_1)),
+            Action::StrafeRight => (KEY_E, Some(GAMEPAD_BUTTON_RIGHT_TRIGGER_1)),
+            Action::Pause => (KEY_P, Some(GAMEPAD_BUTTON_MIDDLE_RIGHT)),
+            Action::Confirm => (KEY_ENTER, Some(GAMEPAD_BUTTON_RIGHT_FACE_DOWN)),
+            Action::Back => (KEY_BACKSPACE, Some(GAMEPAD_BUTTON_RIGHT_FACE_RIGHT)),
+            Action::MenuUp => (KEY_UP, Some(GAMEPAD_BUTTON_LEFT_FACE_UP)),
+            Action::MenuDown => (KEY_DOWN, Some(GAMEPAD_BUTTON_LEFT_FACE_DOWN)),
+            Action::MenuLeft => (KEY_LEFT, Some(GAMEPAD_BUTTON_LEFT_FACE_LEFT)),
+            Action::MenuRight => (KEY_RIGHT, Some(GAMEPAD_BUTTON_LEFT_FACE_RIGHT)),
+            Action::Retry => (KEY_R, Some(GAMEPAD_BUTTON_RIGHT_FACE_LEFT)),
+            Action::ReturnToMenu => (KEY_M, Some(GAMEPAD_BUTTON_MIDDLE_LEFT)),
+            // Not the Confirm button, or one press would do both
+            Action::NextLevel => (KEY_SPACE, Some(GAMEPAD_BUTTON_RIGHT_FACE_UP)),
+        };
+        let mut bindings = vec![Binding::Key(key)];
+        bindings.extend(button.map(Binding::Pad));
+        bindings
+    }
+
+    // Stick axis and direction that drive this action; raylib reports -1.0
+    // for up/left and 1.0 for down/right
+    fn stick_axis(self) -> Option<(GamepadAxis, f32)> {
+        use GamepadAxis::*;
+        match self {
+            Action::MoveForward => Some((GAMEPAD_AXIS_LEFT_Y, -1.0)),
+            Action::MoveBackward => Some((GAMEPAD_AXIS_LEFT_Y, 1.0)),
+            Action::StrafeLeft => Some((GAMEPAD_AXIS_LEFT_X, -1.0)),
+            Action::StrafeRight => Some((GAMEPAD_AXIS_LEFT_X, 1.0)),
+            Action::TurnLeft => Some((GAMEPAD_AXIS_RIGHT_X, -1.0)),
+            Action::TurnRight => Some((GAMEPAD_AXIS_RIGHT_X, 1.0)),
+            Action::MenuUp => Some((GAMEPAD_AXIS_LEFT_Y, -1.0)),
+            Action::MenuDown => Some((GAMEPAD_AXIS_LEFT_Y, 1.0)),
+            Action::MenuLeft => Some((GAMEPAD_AXIS_LEFT_X, -1.0)),
+            Action::MenuRight => Some((GAMEPAD_AXIS_LEFT_X, 1.0)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key(KeyboardKey),
+    Pad(GamepadButton),
+}
+
+const GAMEPAD_BUTTONS: [GamepadButton; 17] = [
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB,
+];
+
+// raylib numbers gamepads from 0 and supports up to 4
+const MAX_GAMEPADS: i32 = 4;
+const DEFAULT_DEAD_ZONE: f32 = 0.25;
+// How far a stick has to be pushed to count as a digital press
+const STICK_PRESS_THRESHOLD: f32 = 0.5;
+
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+    pub dead_zone: f32,
+    gamepad: Option<i32>,
+    stick_held: HashMap<Action, bool>,
+    stick_pressed: Vec<Action>,
+    pad_held: Vec<GamepadButton>,
+    pad_pressed: Option<GamepadButton>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|&action| (action, action.default_bindings()))
+            .collect();
+        InputMap {
+            bindings,
+            dead_zone: DEFAULT_DEAD_ZONE,
+            gamepad: None,
+            stick_held: HashMap::new(),
+            stick_pressed: Vec::new(),
+            pad_held: Vec::new(),
+            pad_pressed: None,
+        }
+    }
+
+    // The player's bindings if they have any, the shipped ones otherwise
+    pub fn load_bindings() -> Self {
+        if std::path::Path::new(BINDINGS_FILE).exists() {
+            InputMap::load(BINDINGS_FILE)
+        } else {
+            InputMap::load(DEFAULT_BINDINGS_FILE)
+        }
+    }
+
+    // Defaults overridden by whatever the bindings file defines
+    pub fn load(path: &str) -> Self {
+        let mut map = InputMap::new();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return map,
+        };
+
+        for (line_no, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                println!("{}:{}: línea ignorada '{}'", path, line_no + 1, line);
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name == "gamepad_dead_zone" {
+                match value.parse::<f32>() {
+                    Ok(dead_zone) => map.dead_zone = dead_zone.clamp(0.0, 0.9),
+                    Err(_) => println!("{}:{}: zona muerta inválida '{}'", path, line_no + 1, value),
+                }
+                continue;
+            }
+            let Some(action) = Action::from_name(name) else {
+                println!("{}:{}: acción desconocida '{}'", path, line_no + 1, name);
+                continue;
+            };
+            let mut parsed = Vec::new();
+            for binding_name in value.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+                match parse_binding(binding_name) {
+                    Some(binding) => parsed.push(binding),
+                    None => println!("{}:{}: tecla desconocida '{}'", path, line_no + 1, binding_name),
+                }
+            }
+            if !parsed.is_empty() {
+                map.bindings.insert(action, parsed);
+            }
+        }
+        map
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let mut contents = String::from("# Pac-Man 3D bindings: action = KEY, PAD_BUTTON\n");
+        contents.push_str(&format!("gamepad_dead_zone = {:.2}\n", self.dead_zone));
+        for action in Action::ALL {
+            let names: Vec<String> = self.bindings(action).iter().map(|&b| binding_name(b)).collect();
+            contents.push_str(&format!("{} = {}\n", action.name(), names.join(", ")));
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // Replaces the bindings of the same kind, so rebinding a key keeps the
+    // gamepad button and vice versa
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let same_kind = |b: &Binding| matches!(
+            (b, binding),
+            (Binding::Key(_), Binding::Key(_)) | (Binding::Pad(_), Binding::Pad(_))
+        );
+        let list = self.bindings.entry(action).or_default();
+        list.retain(|b| !same_kind(b));
+        list.push(binding);
+    }
+
+    pub fn reset(&mut self) {
+        let dead_zone = self.dead_zone;
+        let gamepad = self.gamepad;
+        *self = InputMap::new();
+        self.dead_zone = dead_zone;
+        self.gamepad = gamepad;
+    }
+
+    // Called once per frame: tracks gamepads being plugged in or removed,
+    // turns stick flicks into presses for the menu and notes which button
+    // went down this frame
+    pub fn poll(&mut self, rl: &RaylibHandle) {
+        let still_there = self.gamepad.is_some_and(|pad| rl.is_gamepad_available(pad));
+        if !still_there {
+            if let Some(pad) = self.gamepad.take() {
+                println!("Mando {} desconectado", pad);
+            }
+            self.gamepad = (0..MAX_GAMEPADS).find(|&pad| rl.is_gamepad_available(pad));
+            if let Some(pad) = self.gamepad {
+                let name = rl.get_gamepad_name(pad).unwrap_or_default();
+                println!("Mando {} conectado: {}", pad, name);
+            }
+        }
+
+        self.stick_pressed.clear();
+        for action in Action::ALL {
+            let held = self.axis_value(rl, action) > STICK_PRESS_THRESHOLD;
+            let was_held = self.stick_held.insert(action, held).unwrap_or(false);
+            if held && !was_held {
+                self.stick_pressed.push(action);
+            }
+        }
+
+        let held: Vec<GamepadButton> = match self.gamepad {
+            Some(pad) => GAMEPAD_BUTTONS
+                .iter()
+                .copied()
+                .filter(|&button| rl.is_gamepad_button_down(pad, button))
+                .collect(),
+            None => Vec::new(),
+        };
+        self.pad_pressed = held.iter().copied().find(|button| !self.pad_held.contains(button));
+        self.pad_held = held;
+    }
+
+    // A gamepad button that went down this frame. raylib's own
+    // get_gamepad_button_pressed reports any button still held, which would
+    // bind the button used to start rebinding.
+    pub fn pad_button_pressed(&self) -> Option<GamepadButton> {
+        self.pad_pressed
+    }
+
+    pub fn gamepad_connected(&self) -> bool {
+        self.gamepad.is_some()
+    }
+
+    // Keys and gamepad buttons only, sticks excluded
+    pub fn is_button_down(&self, rl: &RaylibHandle, action: Action) -> bool {
+        self.bindings(action).iter().any(|&binding| match binding {
+            Binding::Key(key) => rl.is_key_down(key),
+            Binding::Pad(button) => self
+                .gamepad
+                .is_some_and(|pad| rl.is_gamepad_button_down(pad, button)),
+        })
+    }
+
+    pub fn any_pressed(&self, rl: &RaylibHandle) -> bool {
+        Action::ALL.iter().any(|&action| self.is_pressed(rl, action))
+    }
+
+    pub fn is_pressed(&self, rl: &RaylibHandle, action: Action) -> bool {
+        let button_pressed = self.bindings(action).iter().any(|&binding| match binding {
+            Binding::Key(key) => rl.is_key_pressed(key),
+            Binding::Pad(button) => self
+                .gamepad
+                .is_some_and(|pad| rl.is_gamepad_button_pressed(pad, button)),
+        });
+        button_pressed || self.stick_pressed.contains(&action)
+    }
+
+    // How hard the stick mapped to this action is pushed, 0.0 to 1.0 after
+    // removing the dead zone
+    pub fn axis_value(&self, rl: &RaylibHandle, action: Action) -> f32 {
+        let Some(pad) = self.gamepad else {
+            return 0.0;
+        };
+        let Some((axis, sign)) = action.stick_axis() else {
+            return 0.0;
+        };
+        let raw = rl.get_gamepad_axis_movement(pad, axis) * sign;
+        if raw <= self.dead_zone {
+            return 0.0;
+        }
+        ((raw - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0)
+    }
+
+    // Full strength for a key or button, proportional for a stick
+    pub fn strength(&self, rl: &RaylibHandle, action: Action) -> f32 {
+        if self.is_button_down(rl, action) {
+            1.0
+        } else {
+            self.axis_value(rl, action)
+        }
+    }
+
+    pub fn describe(&self, action: Action) -> String {
+        // Only show gamepad buttons while a gamepad is plugged in
+        let names: Vec<String> = self
+            .bindings(action)
+            .iter()
+            .filter(|b| self.gamepad_connected() || matches!(b, Binding::Key(_)))
+            .map(|&b| binding_name(b))
+            .collect();
+        if names.is_empty() {
+            "---".to_string()
+        } else {
+            names.join(" / ")
+        }
+    }
+}
+
+pub fn key_name(key: KeyboardKey) -> String {
+    let name = format!("{:?}", key);
+    name.trim_start_matches("KEY_").to_string()
+}
+
+pub fn binding_name(binding: Binding) -> String {
+    match binding {
+        Binding::Key(key) => key_name(key),
+        Binding::Pad(button) => {
+            let name = format!("{:?}", button);
+            format!("PAD_{}", name.trim_start_matches("GAMEPAD_BUTTON_"))
+        }
+    }
+}
+
+fn parse_binding(name: &str) -> Option<Binding> {
+    if name.len() > 4 && name[..4].eq_ignore_ascii_case("PAD_") {
+        return GAMEPAD_BUTTONS
+            .iter()
+            .copied()
+            .map(Binding::Pad)
+            .find(|&b| binding_name(b).eq_ignore_ascii_case(name));
+    }
+    (0..400)
+        .filter_map(key_from_i32)
+        .find(|&key| key_name(key).eq_ignore_ascii_case(name))
+        .map(Binding::Key)
+}
+
+pub fn sample_input(rl: &RaylibHandle, controls: &ControlSettings, input: &InputMap) -> PlayerInput {
+    // Keys give full strength, sticks give proportional movement
+    let forward = input.strength(rl, Action::MoveForward) - input.strength(rl, Action::MoveBackward);
+    let mut strafe = input.strength(rl, Action::StrafeRight) - input.strength(rl, Action::StrafeLeft);
+    let (turn, look) = match controls.scheme {
+        ControlScheme::Classic => {
+            let turn = input.strength(rl, Action::TurnRight) - input.strength(rl, Action::TurnLeft);
+            (turn, 0.0)
+        }
+        ControlScheme::Mouse => {
+            // Only read the mouse while it is captured, otherwise moving it
+            // over the window would spin the camera
+            let look = if rl.is_cursor_hidden() {
+                rl.get_mouse_delta().x * MOUSE_LOOK_SCALE * controls.mouse_sensitivity
+            } else {
+                0.0
+            };
+            if input.is_button_down(rl, Action::TurnRight) {
+                strafe += 1.0;
+            }
+            if input.is_button_down(rl, Action::TurnLeft) {
+                strafe -= 1.0;
+            }
+            // The right stick keeps looking around, turn keys strafe instead
+            let turn = input.axis_value(rl, Action::TurnRight) - input.axis_value(rl, Action::TurnLeft);
+            (turn, look)
+        }
+    };
+
+    PlayerInput {
+        forward,
+        strafe: strafe.clamp(-1.0, 1.0),
+        turn,
+        look,
+    }
+}
@@ -0,0 +1,349 @@
+use std::fs;
+use crate::brain::{DEFAULT_BRAIN, brain_by_name};
+use crate::ghost::{GhostState, Personality};
+use crate::math::fnv1a;
+use crate::player::BASE_SPEED;
+use crate::scripting::SCRIPT_BRAIN_PREFIX;
+
+pub const GHOSTS_FILE: &str = "ghosts.cfg";
+// Section of the ghosts file with settings for the house instead of a ghost
+const HOUSE_SECTION: &str = "house";
+
+// r, g, b, a
+pub type Rgba = [u8; 4];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameVariant {
+    Classic,
+    Stealth,    // ghosts only chase what they see or hear
+    TimeAttack, // race the shadow of your best run through the first level
+}
+
+// One ghost as described in the ghosts file
+#[derive(Clone)]
+pub struct GhostSpec {
+    pub name: String,
+    pub model: String,
+    pub color: Rgba,
+    pub personality: Personality,
+    pub brain: String, // registered GhostBrain that steers it
+    pub spawn: (i32, i32),
+    pub release_delay: f32, // seconds before it starts moving
+    pub from_level: u32,    // first level it takes part in
+    // Pellets it waits for inside the house, per level; the last entry
+    // covers every later level
+    pub dot_limits: Vec<u32>,
+    // Pellets on the shared counter used after the player loses a life
+    pub global_dot_limit: u32,
+}
+
+impl GhostSpec {
+    fn new(name: &str) -> Self {
+        GhostSpec {
+            name: name.to_string(),
+            model: "assets/red_ghost.glb".to_string(),
+            color: [230, 41, 55, 255],
+            personality: Personality::Blinky,
+            brain: DEFAULT_BRAIN.to_string(),
+            spawn: (10, 7),
+            release_delay: 0.0,
+            from_level: 1,
+            dot_limits: vec![0],
+            global_dot_limit: 0,
+        }
+    }
+
+    pub fn dot_limit(&self, level: u32) -> u32 {
+        let index = (level.max(1) as usize - 1).min(self.dot_limits.len().saturating_sub(1));
+        self.dot_limits.get(index).copied().unwrap_or(0)
+    }
+}
+
+// Arcade seconds without eating before the next ghost is pushed out of the
+// house, per level; later levels use the last entry
+const DEFAULT_HOUSE_TIMERS: [f32; 5] = [4.0, 4.0, 4.0, 4.0, 3.0];
+
+// Everything the ghosts file defines: the ghosts and the house settings
+// they share
+#[derive(Clone)]
+pub struct GhostConfig {
+    pub specs: Vec<GhostSpec>,
+    pub house_timers: Vec<f32>,
+    // Hash of the file it was read from, 0 for the built-in defaults
+    pub source_hash: u32,
+}
+
+impl GhostConfig {
+    pub fn house_timer(&self, level: u32) -> f32 {
+        let index = (level.max(1) as usize - 1).min(self.house_timers.len().saturating_sub(1));
+        self.house_timers.get(index).copied().unwrap_or(DEFAULT_HOUSE_TIMERS[0])
+    }
+}
+
+pub fn load_ghost_config(path: &str) -> GhostConfig {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse_ghost_config(&contents),
+        Err(e) => {
+            println!("No se pudo leer {}: {}; usando fantasmas por defecto", path, e);
+            default_ghost_config()
+        }
+    }
+}
+
+// Parses `[name]` sections of `key = value` lines, plus an optional
+// `[house]` section. Unknown keys and bad values are reported and skipped;
+// if no ghost is found the classic four are used instead, and without a
+// house section the arcade timers.
+pub fn parse_ghost_config(contents: &str) -> GhostConfig {
+    // Problems are reported against the file the contents normally come from
+    let path = GHOSTS_FILE;
+    let mut specs: Vec<GhostSpec> = Vec::new();
+    let mut house_timers = DEFAULT_HOUSE_TIMERS.to_vec();
+    let mut in_house = false;
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            in_house = name.trim() == HOUSE_SECTION;
+            if !in_house {
+                specs.push(GhostSpec::new(name.trim()));
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            println!("{}:{}: línea ignorada '{}'", path, line_no + 1, line);
+            continue;
+        };
+        if in_house {
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "idle_timer" => match parse_list(value) {
+                    Some(timers) if !timers.is_empty() => house_timers = timers,
+                    _ => println!("{}:{}: valor inválido para {}: '{}'", path, line_no + 1, key, value),
+                },
+                _ => println!("{}:{}: clave desconocida '{}'", path, line_no + 1, key),
+            }
+            continue;
+        }
+        let Some(spec) = specs.last_mut() else {
+            println!("{}:{}: '{}' fuera de una sección [fantasma]", path, line_no + 1, line);
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let ok = match key {
+            "model" => {
+                spec.model = value.to_string();
+                true
+            }
+            "color" => parse_color(value).map(|color| spec.color = color).is_some(),
+            "personality" => Personality::from_name(value).map(|p| spec.personality = p).is_some(),
+            // Script brains are checked once the script is loaded
+            "brain" if value.starts_with(SCRIPT_BRAIN_PREFIX) => {
+                spec.brain = value.to_string();
+                true
+            }
+            "brain" => brain_by_name(value).map(|brain| spec.brain = brain.name().to_string()).is_some(),
+            "spawn" => parse_tile(value).map(|tile| spec.spawn = tile).is_some(),
+            "release_delay" => value.parse().map(|delay| spec.release_delay = delay).is_ok(),
+            "from_level" => value.parse().map(|level| spec.from_level = level).is_ok(),
+            "dot_limit" => parse_list(value).map(|limits| spec.dot_limits = limits).is_some(),
+            "global_dot_limit" => value.parse().map(|limit| spec.global_dot_limit = limit).is_ok(),
+            _ => {
+                println!("{}:{}: clave desconocida '{}'", path, line_no + 1, key);
+                continue;
+            }
+        };
+        if !ok {
+            println!("{}:{}: valor inválido para {}: '{}'", path, line_no + 1, key, value);
+        }
+    }
+
+    if specs.is_empty() {
+        println!("{} no define ningún fantasma; usando fantasmas por defecto", path);
+        specs = default_ghost_specs();
+    } else {
+        let names: Vec<String> = specs.iter().map(|spec| format!("{} ({})", spec.name, spec.brain)).collect();
+        println!("Fantasmas definidos en {}: {}", path, names.join(", "));
+    }
+    GhostConfig { specs, house_timers, source_hash: fnv1a(contents.bytes()) }
+}
+
+pub(crate) fn default_ghost_config() -> GhostConfig {
+    GhostConfig {
+        specs: default_ghost_specs(),
+        house_timers: DEFAULT_HOUSE_TIMERS.to_vec(),
+        source_hash: 0,
+    }
+}
+
+fn default_ghost_specs() -> Vec<GhostSpec> {
+    use Personality::*;
+    let spec = |name: &str, model: &str, color, personality, spawn| GhostSpec {
+        model: model.to_string(),
+        color,
+        personality,
+        spawn,
+        ..GhostSpec::new(name)
+    };
+    vec![
+        spec("blinky", "assets/red_ghost.glb", [230, 41, 55, 255], Blinky, (9, 7)),
+        GhostSpec {
+            global_dot_limit: 7,
+            ..spec("pinky", "assets/red_ghost.glb", [255, 109, 194, 255], Pinky, (9, 9))
+        },
+        GhostSpec {
+            from_level: 2,
+            dot_limits: vec![30, 0],
+            global_dot_limit: 17,
+            ..spec("inky", "assets/red_ghost.glb", [102, 191, 255, 255], Inky, (8, 9))
+        },
+        GhostSpec {
+            dot_limits: vec![60, 50, 0],
+            global_dot_limit: 32,
+            ..spec("clyde", "assets/yellow_ghost.glb", [255, 161, 0, 255], Clyde, (10, 9))
+        },
+    ]
+}
+
+fn parse_color(value: &str) -> Option<Rgba> {
+    let parts: Vec<u8> = value.split(',').map(|part| part.trim().parse().ok()).collect::<Option<_>>()?;
+    match parts[..] {
+        [r, g, b] => Some([r, g, b, 255]),
+        [r, g, b, a] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+fn parse_list<T: std::str::FromStr>(value: &str) -> Option<Vec<T>> {
+    value.split(',').map(|part| part.trim().parse().ok()).collect()
+}
+
+fn parse_tile(value: &str) -> Option<(i32, i32)> {
+    let (x, y) = value.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+// The ghosts taking part in a level, in spawn order; the menu's ghost count
+// takes the first ones
+pub struct LevelConfig {
+    pub level: u32,
+    pub ghosts: Vec<GhostSpec>,
+    // Seconds without eating before the next ghost is pushed out of the house
+    pub house_timer: f32,
+}
+
+impl LevelConfig {
+    pub fn for_level(level: u32, config: &GhostConfig) -> Self {
+        let ghosts = config.specs.iter().filter(|spec| spec.from_level <= level).cloned().collect();
+        let house_timer = config.house_timer(level);
+        LevelConfig { level, ghosts, house_timer }
+    }
+
+    pub fn max_ghosts(&self) -> usize {
+        self.ghosts.len()
+    }
+}
+
+// Arcade frightened time per level in seconds; later levels use the last entry
+const FRIGHTENED_SECONDS: [f64; 19] = [
+    6.0, 5.0, 4.0, 3.0, 2.0, 5.0, 2.0, 2.0, 1.0, 5.0, 2.0, 1.0, 1.0, 3.0, 1.0, 1.0, 0.0, 1.0, 0.0,
+];
+
+// How hard a level plays: the arcade's per-level table, scaled by the
+// difficulty chosen in the menu, plus the game variant
+pub struct LevelTuning {
+    pub ghost_speed: f32,          // pixels per second
+    pub frightened_duration: f64,  // seconds of power mode per power pellet
+    pub schedule: Vec<(GhostState, f32)>,
+    pub elroy_dots: u32,           // pellets left when Blinky speeds up
+    pub stealth: bool,             // ghosts hunt by sight and sound
+}
+
+impl LevelTuning {
+    pub fn new(level: u32, difficulty: Difficulty, variant: GameVariant) -> Self {
+        use GhostState::{Chase, Scatter};
+        let level = level.max(1);
+
+        let speed_fraction = match level {
+            1 => 0.75,
+            2..=4 => 0.85,
+            _ => 0.95,
+        };
+        let frightened = FRIGHTENED_SECONDS[(level as usize - 1).min(FRIGHTENED_SECONDS.len() - 1)];
+        let schedule = match level {
+            1 => vec![(Scatter, 7.0), (Chase, 20.0), (Scatter, 7.0), (Chase, 20.0), (Scatter, 5.0), (Chase, 20.0), (Scatter, 5.0)],
+            2..=4 => vec![(Scatter, 7.0), (Chase, 20.0), (Scatter, 7.0), (Chase, 20.0), (Scatter, 5.0), (Chase, 1033.0), (Scatter, 1.0 / 60.0)],
+            _ => vec![(Scatter, 5.0), (Chase, 20.0), (Scatter, 5.0), (Chase, 20.0), (Scatter, 5.0), (Chase, 1037.0), (Scatter, 1.0 / 60.0)],
+        };
+        let elroy_dots = match level {
+            1 => 20,
+            2 => 30,
+            3..=5 => 40,
+            6..=8 => 50,
+            9..=11 => 60,
+            12..=14 => 80,
+            15..=18 => 100,
+            _ => 120,
+        };
+
+        // Speed, frightened time, scatter time and Elroy threshold multipliers
+        let (speed_scale, frightened_scale, scatter_scale, elroy_scale) = match difficulty {
+            Difficulty::Easy => (0.8, 1.5, 1.25, 0.5),
+            Difficulty::Medium => (1.0, 1.0, 1.0, 1.0),
+            Difficulty::Hard => (1.15, 0.6, 0.75, 1.5),
+        };
+
+        LevelTuning {
+            ghost_speed: BASE_SPEED * speed_fraction * speed_scale,
+            frightened_duration: frightened * frightened_scale,
+            schedule: schedule
+                .into_iter()
+                .map(|(mode, secs)| if mode == Scatter { (mode, secs * scatter_scale) } else { (mode, secs) })
+                .collect(),
+            elroy_dots: (elroy_dots as f32 * elroy_scale) as u32,
+            stealth: variant == GameVariant::Stealth,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_GHOST: &str = "[blinky]\npersonality = blinky\nspawn = 9, 7\n";
+
+    #[test]
+    fn house_timers_come_from_the_house_section() {
+        let config = parse_ghost_config(&format!("[house]\nidle_timer = 5, 2.5\n\n{}", ONE_GHOST));
+        assert_eq!(config.specs.len(), 1);
+        assert_eq!(config.house_timers, vec![5.0, 2.5]);
+        assert_eq!(LevelConfig::for_level(1, &config).house_timer, 5.0);
+        assert_eq!(LevelConfig::for_level(2, &config).house_timer, 2.5);
+        // The last value covers every later level
+        assert_eq!(LevelConfig::for_level(40, &config).house_timer, 2.5);
+    }
+
+    #[test]
+    fn missing_house_section_uses_the_arcade_timers() {
+        let config = parse_ghost_config(ONE_GHOST);
+        assert_eq!(config.specs.len(), 1);
+        assert_eq!(config.house_timers, DEFAULT_HOUSE_TIMERS.to_vec());
+        assert_eq!(LevelConfig::for_level(1, &config).house_timer, 4.0);
+        assert_eq!(LevelConfig::for_level(5, &config).house_timer, 3.0);
+    }
+
+    #[test]
+    fn bad_house_timers_are_ignored() {
+        let config = parse_ghost_config(&format!("[house]\nidle_timer = soon\n{}", ONE_GHOST));
+        assert_eq!(config.house_timers, DEFAULT_HOUSE_TIMERS.to_vec());
+    }
+}
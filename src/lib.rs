@@ -0,0 +1,20 @@
+// The game without a window: maze, raycasting, player, ghosts and their
+// brains, scoring and the fixed-step simulation that ties them together.
+// The raylib frontend in main.rs is one user of it; tools and bots can be
+// others.
+pub mod math;
+pub mod maze;
+pub mod ray;
+pub mod player;
+pub mod game_state;
+pub mod ghost;
+pub mod brain;
+pub mod level;
+pub mod scripting;
+pub mod stealth;
+pub mod autopilot;
+pub mod sim;
+pub mod replay;
+pub mod shadow;
+pub mod scores;
+pub mod soak;
@@ -1,74 +1,187 @@
 use raylib::prelude::{
-    Color, Vector2, Vector3, Camera3D, Model, Image, Texture2D, RaylibHandle, RaylibThread, RaylibDrawHandle, KeyboardKey,
-    RaylibDraw, RaylibTexture2D, RaylibMode3DExt, RaylibDraw3D
+    Color, Vector3, Camera3D, Model, Texture2D, RaylibHandle, RaylibDrawHandle,
+    RaylibDraw, RaylibTexture2D, RaylibMode3DExt, RaylibDraw3D, RaylibAudio
 };
+use std::rc::Rc;
 
-mod player;
-mod maze;
-mod ray;
 mod textures;
-mod game_state;
-
-use player::{Player, process_events};
-use crate::player::check_collision;
-use maze::{Maze, CELL_SIZE, Cell, WIDTH, HEIGHT};
-use ray::cast_ray;
+mod input;
+mod menu;
+mod assets;
+mod hud;
+mod scoreboard;
+
+use pacman::player::{Player, MovementMode};
+use pacman::maze::{Maze, CELL_SIZE, Cell, WIDTH, HEIGHT};
+use pacman::ray::cast_ray;
+use pacman::game_state::{GameMode, POPUP_LIFETIME};
+use pacman::ghost::{Ghost, GhostState};
+use pacman::level::{GameVariant, Rgba, GHOSTS_FILE, load_ghost_config};
+use pacman::scripting::{Scripts, SCRIPT_FILE};
+use pacman::autopilot::Autopilot;
+use pacman::sim::{SimConfig, SimEvent, Simulation, SIM_DT};
+use pacman::soak::run_soak;
+use pacman::replay::{Playback, Replay, REPLAY_FILE};
+use pacman::shadow::{Shadow, best_run_path};
+use pacman::math::Vec2;
+use pacman::scores::{HighScores, ScoreEntry, HIGH_SCORES_FILE};
 use textures::TextureManager;
-use game_state::{GameState, GameMode};
+use input::{Action, ControlSettings, InputMap, sample_input};
+use menu::MenuState;
+use assets::{AssetCache, SOUND_CHOMP, SOUND_EAT_GHOST, SOUND_POWER};
+use scoreboard::{NameEntry, draw_table};
 
-#[derive(Clone, Copy)]
-enum Difficulty {
-    Easy,
-    Medium,
-    Hard,
+const FOV: f32 = std::f32::consts::FRAC_PI_3;
+// Longest frame the simulation will catch up on, so a stall (window drag,
+// breakpoint) doesn't trigger a burst of hundreds of ticks
+const MAX_FRAME_TIME: f32 = 0.1;
+const NUM_RAYS: i32 = 800;
+// In stealth mode walls fade to black by this distance, in pixels
+const FOG_DISTANCE: f32 = 8.0 * CELL_SIZE as f32;
+// Frightened ghosts start flashing when this many seconds of power mode remain
+const FLASH_TIME: f64 = 2.0;
+// Seconds skipped by one seek press while watching a replay
+const SEEK_SECONDS: f32 = 5.0;
+const MAX_PLAYBACK_SPEED: f32 = 16.0;
+const MIN_PLAYBACK_SPEED: f32 = 0.25;
+
+// Attract mode: the bot plays on its own until a key is pressed. The
+// high score from before it started is put back afterwards.
+struct Demo {
+    bot: Autopilot,
+    high_score: u32,
 }
 
-struct MenuState {
-    num_ghosts: usize,
-    difficulty: Difficulty,
-    selected: usize, // 0 = ghosts, 1 = difficulty, 2 = start
+// Time attack: the current run against the best one on this maze with the
+// same difficulty, ghosts and movement, until the first level is cleared
+struct Race {
+    best: Option<Shadow>,
 }
 
-const FOV: f32 = std::f32::consts::FRAC_PI_3;
-const NUM_RAYS: i32 = 800;
+// Keeps the finished run as the maze's best if it beat the shadow
+fn finish_race(race: Race, run: &Replay, sim: &Simulation) {
+    match race.best {
+        Some(best) if best.ticks() <= run.inputs.len() => {
+            println!("Tiempo: {:.2} s (récord: {:.2} s)", run.seconds(), best.replay.seconds());
+        }
+        _ => {
+            println!("¡Nuevo récord: {:.2} s!", run.seconds());
+            run.save(&best_run_path(sim));
+        }
+    }
+}
+
+// Starts a player's game: its recording, the high score to beat on this
+// maze and difficulty, and in time attack the race against the best run
+fn begin_game(sim: &mut Simulation, config: SimConfig, scores: &HighScores) -> (Option<Replay>, Option<Race>) {
+    sim.start_game(config);
+    sim.game_state.high_score = scores.best(sim.maze.layout_id(), config.difficulty);
+    let race = (config.variant == GameVariant::TimeAttack).then(|| Race { best: Shadow::load_best(sim) });
+    (Some(Replay::new(sim)), race)
+}
+
+fn color(rgba: Rgba) -> Color {
+    Color::new(rgba[0], rgba[1], rgba[2], rgba[3])
+}
+
+fn game_config(menu: &MenuState, movement: MovementMode, seed: u64) -> SimConfig {
+    SimConfig {
+        difficulty: menu.difficulty,
+        variant: menu.variant,
+        num_ghosts: menu.num_ghosts,
+        movement,
+        seed,
+        start_level: 1,
+    }
+}
+
+// Tint for a ghost's model; None means only the eyes are drawn
+fn ghost_tint(ghost: &Ghost, power_remaining: f64, time: f64) -> Option<Color> {
+    match ghost.state {
+        GhostState::Eaten => None,
+        GhostState::Frightened => {
+            let flash = power_remaining < FLASH_TIME && (time * 4.0) as i64 % 2 == 0;
+            Some(if flash { Color::WHITE } else { Color::BLUE })
+        }
+        _ => Some(color(ghost.color)),
+    }
+}
+
+// Pause, next level and leaving a cleared level for the menu; movement
+// reaches the simulation as a PlayerInput
+fn handle_game_input(sim: &mut Simulation, rl: &RaylibHandle, input: &InputMap, recording: &mut Option<Replay>) {
+    match sim.game_state.mode {
+        GameMode::Playing | GameMode::Paused if input.is_pressed(rl, Action::Pause) => {
+            sim.game_state.pause_game();
+        }
+        GameMode::LevelComplete if input.is_pressed(rl, Action::NextLevel) => {
+            if let Some(replay) = recording.as_mut() {
+                replay.record_next_level();
+            }
+            sim.next_level();
+        }
+        // The game ends on the cleared level, so the replay ends there too
+        GameMode::LevelComplete if input.is_pressed(rl, Action::ReturnToMenu) => {
+            if let Some(replay) = recording.take() {
+                replay.save(REPLAY_FILE);
+            }
+            sim.game_state.mode = GameMode::Menu;
+        }
+        _ => {}
+    }
+}
 
-struct Ghost {
-    pos: Vector2,
-    speed: f32,
-    model: Model,
-    color: Color,
+// Replay controls: pause, speed, and seeking by re-simulation. Returns true
+// after a seek, whose skipped ticks shouldn't all make their sounds at once.
+fn handle_playback_input(playback: &mut Playback, sim: &mut Simulation, rl: &RaylibHandle, input: &InputMap) -> bool {
+    if input.is_pressed(rl, Action::Pause) {
+        playback.paused = !playback.paused;
+    }
+    if input.is_pressed(rl, Action::MenuUp) {
+        playback.speed = (playback.speed * 2.0).min(MAX_PLAYBACK_SPEED);
+    }
+    if input.is_pressed(rl, Action::MenuDown) {
+        playback.speed = (playback.speed / 2.0).max(MIN_PLAYBACK_SPEED);
+    }
+    let seek_ticks = (SEEK_SECONDS / SIM_DT) as usize;
+    if input.is_pressed(rl, Action::MenuLeft) {
+        playback.seek(sim, playback.tick.saturating_sub(seek_ticks));
+        return true;
+    }
+    if input.is_pressed(rl, Action::MenuRight) {
+        playback.seek(sim, playback.tick + seek_ticks);
+        return true;
+    }
+    false
 }
 
-fn spawn_ghosts(
-    rl: &mut RaylibHandle,
-    thread: &RaylibThread,
-    num_ghosts: usize,
-    difficulty: Difficulty,
-) -> Vec<Ghost> {
-    let mut result = Vec::new();
-    let ghost_specs = [
-        ("assets/red_ghost.glb", Color::RED, 10.0, 13.0),
-        ("assets/yellow_ghost.glb", Color::YELLOW, 10.0, 11.0),
-    ];
-    let base_speed = match difficulty {
-        Difficulty::Easy => 1.0,
-        Difficulty::Medium => 2.0,
-        Difficulty::Hard => 3.0,
-    };
-    for i in 0..num_ghosts {
-        let (model_path, color, gx, gy) = ghost_specs[i];
-        let model = rl.load_model(thread, model_path).expect("Failed to load ghost model");
-        result.push(Ghost {
-            pos: Vector2::new(
-                gx * CELL_SIZE as f32 + CELL_SIZE as f32 / 2.0,
-                gy * CELL_SIZE as f32 + CELL_SIZE as f32 / 2.0,
-            ),
-            speed: base_speed,
-            model,
-            color,
-        });
+// `--soak [games]` on the command line
+fn soak_games() -> Option<u32> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--soak")?;
+    Some(args.get(index + 1).and_then(|games| games.parse().ok()).unwrap_or(1000))
+}
+
+// `--seed N` on the command line plays every game with that seed
+fn seed_arg() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--seed")?;
+    let seed = args.get(index + 1).and_then(|seed| seed.parse().ok());
+    if seed.is_none() {
+        println!("--seed necesita un número; se usan semillas aleatorias");
+    }
+    seed
+}
+
+// `--replay FILE` on the command line watches a recorded game
+fn replay_arg() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--replay")?;
+    let path = args.get(index + 1).cloned();
+    if path.is_none() {
+        println!("--replay necesita un archivo");
     }
-    result
+    path
 }
 
 fn main() {
@@ -80,6 +193,20 @@ fn main() {
         return;
     }
 
+    let scripts = Scripts::load(SCRIPT_FILE);
+    let ghost_config = load_ghost_config(GHOSTS_FILE);
+
+    let fixed_seed = seed_arg();
+    let next_seed = || fixed_seed.unwrap_or_else(rand::random);
+
+    // Soak runs need no window at all
+    if let Some(games) = soak_games() {
+        let first_seed = next_seed();
+        println!("Soak: semillas desde {}", first_seed);
+        let passed = run_soak(&ghost_config, scripts.as_ref(), games, first_seed);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     let screen_width = 800;
     let screen_height = 800;
 
@@ -90,150 +217,200 @@ fn main() {
 
     rl.set_target_fps(60);
 
-    let mut maze = Maze::new();
-    let mut player = Player::new(10, 15);
-    let texture_manager = TextureManager::new(&mut rl, &thread);
-    let mut game_state = GameState::new(&rl);
+    // Everything is loaded up front through the cache, which prints a
+    // single report; missing files fall back instead of aborting
+    let audio = RaylibAudio::init_audio_device().ok();
+    let mut assets = AssetCache::new(&mut rl, &thread, audio.as_ref());
+    let texture_manager = TextureManager::new(&mut rl, &thread, &mut assets);
 
-    // --- Load animated GIF for moneda.gif as coin frames
-    let mut anim_frames = 0;
-    let mut coin_textures: Vec<Texture2D> = Vec::new();
-    let coin_image = Image::load_image_anim("assets/moneda.gif", &mut anim_frames);
-    // SAFE: Only use the first frame (no unsafe pointer manipulation or per-frame copying)
-    let tex = rl.load_texture_from_image(&thread, &coin_image).unwrap();
-    coin_textures.push(tex);
+    // Coin frames; only the first frame of moneda.gif is used for now
+    let coin_textures: Vec<Rc<Texture2D>> = assets.texture(&mut rl, &thread, "assets/moneda.gif").into_iter().collect();
 
-    // Menu state
-    let mut menu_state = MenuState {
-        num_ghosts: 2,
-        difficulty: Difficulty::Medium,
-        selected: 0,
-    };
-    let mut ghosts: Vec<Ghost> = Vec::new();
+    for spec in ghost_config.specs.iter() {
+        assets.preload_model(&mut rl, &thread, &spec.model);
+    }
+    assets.preload_sounds();
+    assets.flush_report();
 
-    // Start in menu
-    game_state.mode = GameMode::Menu;
+    let mut sim = Simulation::new(ghost_config, scripts);
+    // Models for sim.ghosts, by index; rebuilt whenever the ghosts respawn
+    let mut ghost_models: Vec<Option<Rc<Model>>> = Vec::new();
+
+    // Menu state
+    let mut menu_state = MenuState::new(sim.max_ghosts());
+    let mut controls = ControlSettings::new();
+    let mut input = InputMap::load_bindings();
+    let mut accumulator: f32 = 0.0;
+    // Mouse look from frames that ran no tick, owed to the next one
+    let mut pending_look: f32 = 0.0;
+    let mut demo: Option<Demo> = None;
+    // Every game played is recorded and saved to REPLAY_FILE once it ends
+    let mut recording: Option<Replay> = None;
+    let mut race: Option<Race> = None;
+    let mut scores = HighScores::load(HIGH_SCORES_FILE);
+    let maze_id = sim.maze.layout_id();
+    // Initials being entered after a game that made the table, then the
+    // place it got there
+    let mut name_entry: Option<NameEntry> = None;
+    let mut last_rank: Option<usize> = None;
+    let mut playback = replay_arg().and_then(|path| {
+        let replay = Replay::load(&path)?;
+        replay.check_data(&sim, &path);
+        Some(Playback::new(replay))
+    });
+
+    if let Some(active) = playback.as_mut() {
+        active.restart(&mut sim);
+    } else {
+        // Start in menu
+        sim.game_state.mode = GameMode::Menu;
+    }
 
     while !rl.window_should_close() {
-        game_state.update(&rl);
-        game_state.handle_input(&rl);
-
-        // Forcibly restart the program after Game Over 5s
-        if game_state.is_game_over() {
-            if let Some(game_over_time) = game_state.game_over_time {
-                if rl.get_time() > game_over_time + 5.0 {
-                    // Relaunch the executable and exit this process (hard reboot)
-                    std::process::Command::new(std::env::current_exe().unwrap()).spawn().unwrap();
-                    std::process::exit(0);
-                }
+        let frame_time = rl.get_frame_time().min(MAX_FRAME_TIME);
+        input.poll(&rl);
+        let mut seeked = false;
+        if let Some(active) = playback.as_mut() {
+            seeked = handle_playback_input(active, &mut sim, &rl, &input);
+            if input.is_pressed(&rl, Action::ReturnToMenu) {
+                playback = None;
+                sim = sim.fresh();
+                sim.game_state.mode = GameMode::Menu;
             }
+        } else {
+            handle_game_input(&mut sim, &rl, &input, &mut recording);
         }
 
-        // MENU
-        if game_state.mode == GameMode::Menu {
-            if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
-                menu_state.selected = (menu_state.selected + 1) % 3;
-            }
-            if rl.is_key_pressed(KeyboardKey::KEY_UP) {
-                menu_state.selected = (menu_state.selected + 2) % 3;
+        // Capture the cursor only while actually playing with mouse look
+        let capture_mouse = playback.is_none() && sim.game_state.is_playing() && controls.wants_mouse_capture();
+        if capture_mouse && !rl.is_cursor_hidden() {
+            rl.disable_cursor();
+        } else if !capture_mouse && rl.is_cursor_hidden() {
+            rl.enable_cursor();
+        }
+
+        // The demo ends on any key, or once the bot loses or clears the level
+        if let Some(active) = &demo {
+            let finished = matches!(sim.game_state.mode, GameMode::GameOver | GameMode::LevelComplete);
+            if finished || input.any_pressed(&rl) {
+                let high_score = active.high_score;
+                demo = None;
+                sim.game_state.reset_game();
+                sim.game_state.high_score = high_score;
+                sim.game_state.mode = GameMode::Menu;
             }
-            if menu_state.selected == 0 {
-                if rl.is_key_pressed(KeyboardKey::KEY_LEFT) || rl.is_key_pressed(KeyboardKey::KEY_ONE) {
-                    menu_state.num_ghosts = 1;
-                }
-                if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) || rl.is_key_pressed(KeyboardKey::KEY_TWO) {
-                    menu_state.num_ghosts = 2;
-                }
+        }
+
+        // Game over: initials for the table, then retry or back to the menu.
+        // Retrying or leaving skips the initials.
+        if sim.game_state.is_game_over() && playback.is_none() {
+            if let Some(name) = name_entry.as_mut().and_then(|entry| entry.update(&rl, &input)) {
+                name_entry = None;
+                let entry = ScoreEntry { name, score: sim.game_state.score, level: sim.game_state.level };
+                last_rank = scores.insert(maze_id, sim.config.difficulty, entry);
+                scores.save(HIGH_SCORES_FILE);
             }
-            if menu_state.selected == 1 {
-                if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
-                    menu_state.difficulty = match menu_state.difficulty {
-                        Difficulty::Easy => Difficulty::Hard,
-                        Difficulty::Medium => Difficulty::Easy,
-                        Difficulty::Hard => Difficulty::Medium,
-                    };
-                }
-                if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
-                    menu_state.difficulty = match menu_state.difficulty {
-                        Difficulty::Easy => Difficulty::Medium,
-                        Difficulty::Medium => Difficulty::Hard,
-                        Difficulty::Hard => Difficulty::Easy,
-                    };
-                }
+            if input.is_pressed(&rl, Action::Retry) {
+                let config = SimConfig { seed: next_seed(), ..sim.config };
+                (recording, race) = begin_game(&mut sim, config, &scores);
+                accumulator = 0.0;
+            } else if input.is_pressed(&rl, Action::ReturnToMenu) {
+                sim.game_state.mode = GameMode::Menu;
             }
-            if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
-                ghosts = spawn_ghosts(&mut rl, &thread, menu_state.num_ghosts, menu_state.difficulty);
-                maze = Maze::new();
-                player = Player::new(10, 15);
-                game_state.mode = GameMode::Playing;
+            if !sim.game_state.is_game_over() {
+                name_entry = None;
+                last_rank = None;
             }
         }
 
-        if game_state.is_playing() {
-            process_events(&mut player, &maze, &rl, &game_state);
-
-            let grid_x = (player.pos.x / CELL_SIZE as f32) as usize;
-            let grid_y = (player.pos.y / CELL_SIZE as f32) as usize;
-
-            if let Some(points) = maze.collect_pellet(grid_x, grid_y) {
-                game_state.add_score(points);
+        // MENU
+        if sim.game_state.mode == GameMode::Menu {
+            if menu_state.update(&mut rl, &mut input, &mut controls) {
+                let config = game_config(&menu_state, controls.movement, next_seed());
+                (recording, race) = begin_game(&mut sim, config, &scores);
+                accumulator = 0.0;
+            } else if menu_state.update_idle(&rl, &input, frame_time) {
+                demo = Some(Demo { bot: Autopilot::new(), high_score: sim.game_state.high_score });
+                sim.start_game(game_config(&menu_state, controls.movement, next_seed()));
+                accumulator = 0.0;
+            }
+        }
 
-                if points == 50 {
-                    game_state.activate_power_mode();
+        if let Some(active) = playback.as_mut() {
+            if !active.paused {
+                accumulator += frame_time * active.speed;
+            }
+            while accumulator >= SIM_DT && !active.is_finished() {
+                active.step(&mut sim);
+                accumulator -= SIM_DT;
+            }
+            accumulator = accumulator.min(SIM_DT);
+        } else if !matches!(sim.game_state.mode, GameMode::Menu | GameMode::Paused) {
+            // The menu and pause screens hold the simulation still
+            let mut cmd = sample_input(&rl, &controls, &input);
+            cmd.look += pending_look;
+            accumulator += frame_time;
+            while accumulator >= SIM_DT {
+                if let Some(active) = demo.as_mut() {
+                    cmd = active.bot.drive(&sim.player, &sim.maze, &sim.ghosts);
+                }
+                sim.tick(&cmd);
+                if let Some(replay) = recording.as_mut() {
+                    replay.record(&cmd);
+                }
+                if sim.game_state.is_level_complete()
+                    && let (Some(done), Some(run)) = (race.take(), recording.as_ref())
+                {
+                    finish_race(done, run, &sim);
                 }
+                // Mouse look is a per-frame delta, only the first tick gets it
+                cmd.look = 0.0;
+                accumulator -= SIM_DT;
             }
+            pending_look = cmd.look;
+        } else {
+            pending_look = 0.0;
+        }
 
-            if maze.is_level_complete() {
-                game_state.complete_level(&rl);
+        // A finished game is saved right away
+        if sim.game_state.is_game_over() {
+            race = None;
+            if let Some(replay) = recording.take() {
+                replay.save(REPLAY_FILE);
+                if scores.qualifies(maze_id, sim.config.difficulty, sim.game_state.score) {
+                    name_entry = Some(NameEntry::new());
+                }
             }
+        }
 
-            // Move ghosts toward player
-            for ghost in ghosts.iter_mut() {
-                ghost.speed = player.speed * 0.75;
-                let dx = player.pos.x - ghost.pos.x;
-                let dy = player.pos.y - ghost.pos.y;
-                let mut moved = false;
-                if dx.abs() > dy.abs() {
-                    let step_x = ghost.speed * dx.signum();
-                    let next_x = ghost.pos.x + step_x;
-                    if !check_collision(next_x, ghost.pos.y, &maze) {
-                        ghost.pos.x = next_x;
-                        moved = true;
-                    }
-                    if !moved {
-                        let step_y = ghost.speed * dy.signum();
-                        let next_y = ghost.pos.y + step_y;
-                        if !check_collision(ghost.pos.x, next_y, &maze) {
-                            ghost.pos.y = next_y;
-                        }
-                    }
-                } else {
-                    let step_y = ghost.speed * dy.signum();
-                    let next_y = ghost.pos.y + step_y;
-                    if !check_collision(ghost.pos.x, next_y, &maze) {
-                        ghost.pos.y = next_y;
-                        moved = true;
-                    }
-                    if !moved {
-                        let step_x = ghost.speed * dx.signum();
-                        let next_x = ghost.pos.x + step_x;
-                        if !check_collision(next_x, ghost.pos.y, &maze) {
-                            ghost.pos.x = next_x;
-                        }
-                    }
-                }
-                if (ghost.pos.x - player.pos.x).abs() < CELL_SIZE as f32 / 2.0 && (ghost.pos.y - player.pos.y).abs() < CELL_SIZE as f32 / 2.0 {
-                    game_state.mode = game_state::GameMode::GameOver;
+        for event in sim.take_events() {
+            match event {
+                SimEvent::GhostsSpawned => {
+                    ghost_models = sim.ghosts.iter().map(|ghost| assets.model(&mut rl, &thread, &ghost.model)).collect();
+                    assets.release_unused();
+                    assets.flush_report();
                 }
-            }
-        } else if game_state.is_level_complete() {
-            if game_state.is_playing() {
-                maze = Maze::new();
-                player = Player::new(10, 15);
-                ghosts = spawn_ghosts(&mut rl, &thread, menu_state.num_ghosts, menu_state.difficulty);
+                _ if seeked => {}
+                SimEvent::PelletEaten => assets.play_sound(SOUND_CHOMP),
+                SimEvent::PowerPelletEaten => assets.play_sound(SOUND_POWER),
+                SimEvent::GhostEaten => assets.play_sound(SOUND_EAT_GHOST),
             }
         }
 
+        // How far we are between the last simulation tick and the next one
+        let alpha = (accumulator / SIM_DT).clamp(0.0, 1.0);
+        let (view_pos, mut view_angle) = sim.player.interpolated(alpha);
+        // Death animation: the view spins faster and faster
+        let death = sim.game_state.death_progress();
+        view_angle += death * death * std::f32::consts::TAU * 2.0;
+        // The best run, as far into it as the current one is
+        let shadow_pos = race
+            .as_ref()
+            .and_then(|race| race.best.as_ref())
+            .zip(recording.as_ref())
+            .and_then(|(best, run)| best.position(run.inputs.len(), alpha));
+
+        let time = rl.get_time();
         let anim_speed = 10.0;
         let frame_idx = if !coin_textures.is_empty() {
             ((rl.get_time() * anim_speed) as usize) % coin_textures.len()
@@ -242,32 +419,20 @@ fn main() {
         let mut d = rl.begin_drawing(&thread);
         d.clear_background(Color::BLACK);
 
-        if game_state.mode == GameMode::Menu {
-            d.draw_text("PAC-MAN 3D", 260, 100, 40, Color::YELLOW);
-            let ghost_str = format!("Number of Ghosts: {}", menu_state.num_ghosts);
-            let diff_str = format!("Difficulty: {}", match menu_state.difficulty {
-                Difficulty::Easy => "Easy",
-                Difficulty::Medium => "Medium",
-                Difficulty::Hard => "Hard",
-            });
-            let start_str = "Start Game";
-            let menu_items = [ghost_str.as_str(), diff_str.as_str(), start_str];
-            for (i, item) in menu_items.iter().enumerate() {
-                let color = if i == menu_state.selected { Color::GREEN } else { Color::WHITE };
-                d.draw_text(item, 300, 200 + i as i32 * 40, 30, color);
-            }
-            d.draw_text("Use UP/DOWN to select, LEFT/RIGHT or 1/2 to change, ENTER to start", 100, 400, 18, Color::LIGHTGRAY);
+        if sim.game_state.mode == GameMode::Menu {
+            menu_state.draw(&mut d, &input, &controls, &scores, maze_id);
         }
 
-        if game_state.is_playing() || game_state.is_paused() {
+        if sim.game_state.shows_maze() {
+            let stealth = sim.config.variant == GameVariant::Stealth;
             for i in 0..NUM_RAYS {
-                let ray_angle = player.angle - FOV / 2.0 + FOV * (i as f32) / (NUM_RAYS as f32);
-                let intersect = cast_ray(player.pos, ray_angle, &maze);
-                let corrected_distance = intersect.distance * (player.angle - ray_angle).cos();
+                let ray_angle = view_angle - FOV / 2.0 + FOV * (i as f32) / (NUM_RAYS as f32);
+                let intersect = cast_ray(view_pos, ray_angle, &sim.maze);
+                let corrected_distance = intersect.distance * (view_angle - ray_angle).cos();
                 let wall_height = (CELL_SIZE as f32 * screen_height as f32) / corrected_distance;
                 let texture_id = texture_manager.get_wall_texture_id(intersect.wall_x, intersect.wall_y);
 
-                if let Some(texture) = texture_manager.textures.get(&texture_id) {
+                if let Some(texture) = texture_manager.get(texture_id) {
                     let tex_width = texture.width() as f32;
                     let tex_height = texture.height() as f32;
                     let tex_x = (intersect.texture_coord * tex_width).clamp(0.0, tex_width - 1.0);
@@ -298,36 +463,42 @@ fn main() {
                         d.draw_pixel(i, y, Color::RED);
                     }
                 }
+
+                // Stealth: the corridors fade into darkness
+                if stealth {
+                    let fog = (corrected_distance / FOG_DISTANCE).min(1.0);
+                    let top = (screen_height as f32 / 2.0 - wall_height / 2.0) as i32;
+                    d.draw_rectangle(i, top, 1, wall_height as i32, Color::new(0, 0, 0, (fog * 255.0) as u8));
+                }
             }
 
             // Animated coin rendering
             if !coin_textures.is_empty() {
                 let coin_draw_size = (coin_textures[0].width().max(coin_textures[0].height()) as f32) * 0.5;
-                let moneda_tex = &coin_textures[frame_idx];
+                let moneda_tex: &Texture2D = &coin_textures[frame_idx];
+                let coin_range = if stealth { FOG_DISTANCE } else { 500.0 };
                 for y in 0..HEIGHT {
                     for x in 0..WIDTH {
-                        match maze.get_cell(x, y) {
+                        match sim.maze.get_cell(x, y) {
                             Cell::Pellet | Cell::PowerPellet => {
                                 let fx = x as f32 * CELL_SIZE as f32 + CELL_SIZE as f32 / 2.0;
                                 let fy = y as f32 * CELL_SIZE as f32 + CELL_SIZE as f32 / 2.0;
-                                let rel_x = fx - player.pos.x;
-                                let rel_y = fy - player.pos.y;
-                                let angle_to_point = rel_y.atan2(rel_x) - player.angle;
+                                let rel_x = fx - view_pos.x;
+                                let rel_y = fy - view_pos.y;
+                                let angle_to_point = rel_y.atan2(rel_x) - view_angle;
                                 let dist = (rel_x*rel_x + rel_y*rel_y).sqrt();
-                                if angle_to_point.abs() < FOV / 2.0 && dist > (CELL_SIZE as f32)/2.0 && dist < 500.0 {
+                                if angle_to_point.abs() < FOV / 2.0 && dist > (CELL_SIZE as f32)/2.0 && dist < coin_range {
                                     let mut blocked = false;
                                     let steps = dist.max(1.0) as i32 / 8;
                                     for step in 1..steps {
                                         let t = step as f32 / steps as f32;
-                                        let cx = player.pos.x + (fx - player.pos.x) * t;
-                                        let cy = player.pos.y + (fy - player.pos.y) * t;
+                                        let cx = view_pos.x + (fx - view_pos.x) * t;
+                                        let cy = view_pos.y + (fy - view_pos.y) * t;
                                         let ix = (cx / CELL_SIZE as f32).floor() as usize;
                                         let iy = (cy / CELL_SIZE as f32).floor() as usize;
-                                        if ix < WIDTH && iy < HEIGHT {
-                                            if matches!(maze.get_cell(ix, iy), Cell::Wall) {
-                                                blocked = true;
-                                                break;
-                                            }
+                                        if ix < WIDTH && iy < HEIGHT && matches!(sim.maze.get_cell(ix, iy), Cell::Wall) {
+                                            blocked = true;
+                                            break;
                                         }
                                     }
                                     if !blocked {
@@ -356,41 +527,105 @@ fn main() {
             }
 
             let camera = Camera3D::perspective(
-                Vector3::new(player.pos.x, 10.0, player.pos.y),
+                Vector3::new(view_pos.x, 10.0, view_pos.y),
                 Vector3::new(
-                    player.pos.x + player.angle.cos() * 10.0,
+                    view_pos.x + view_angle.cos() * 10.0,
                     0.0,
-                    player.pos.y + player.angle.sin() * 10.0,
+                    view_pos.y + view_angle.sin() * 10.0,
                 ),
                 Vector3::new(0.0, 1.0, 0.0),
                 60.0,
             );
             {
-                let mut mode3d = d.begin_mode3D(&camera);
-                for ghost in ghosts.iter() {
-                    let ghost_pos_3d = Vector3::new(ghost.pos.x, 2.0, ghost.pos.y - 2.0);
-                    mode3d.draw_model(&ghost.model, ghost_pos_3d, 1.0, ghost.color);
+                let mut mode3d = d.begin_mode3D(camera);
+                for (ghost, model) in sim.ghosts.iter().zip(ghost_models.iter()) {
+                    let ghost_pos = ghost.prev_pos.lerp(ghost.pos, alpha);
+                    let ghost_pos_3d = Vector3::new(ghost_pos.x, 2.0, ghost_pos.y - 2.0);
+                    match (ghost_tint(ghost, sim.game_state.get_power_mode_remaining(), time), model) {
+                        (Some(tint), Some(model)) => mode3d.draw_model(model.as_ref(), ghost_pos_3d, 1.0, tint),
+                        (Some(tint), None) => {
+                            // Model failed to load: a flat colored sprite still shows where it is
+                            if let Some(fallback) = &assets.fallback {
+                                mode3d.draw_billboard(camera, fallback, ghost_pos_3d, 12.0, tint);
+                            }
+                        }
+                        (None, _) => {
+                            // Eaten: just a pair of eyes floating back home
+                            let side = Vector3::new(-view_angle.sin(), 0.0, view_angle.cos()) * 3.0;
+                            mode3d.draw_sphere(ghost_pos_3d + side, 2.0, Color::WHITE);
+                            mode3d.draw_sphere(ghost_pos_3d - side, 2.0, Color::WHITE);
+                        }
+                    }
+                }
+                if let Some(pos) = shadow_pos {
+                    mode3d.draw_sphere(Vector3::new(pos.x, 4.0, pos.y), 6.0, Color::YELLOW.fade(0.35));
+                }
+            }
+            // Ghost points rise and fade where the ghost was eaten
+            for popup in sim.game_state.popups.iter() {
+                let rel = popup.pos - view_pos;
+                if rel.x * view_angle.cos() + rel.y * view_angle.sin() <= 0.0 {
+                    continue;
                 }
+                let pos_3d = Vector3::new(popup.pos.x, 4.0 + popup.age * 6.0, popup.pos.y);
+                let screen_pos = d.get_world_to_screen(pos_3d, camera);
+                let fade = 1.0 - popup.age / POPUP_LIFETIME;
+                d.draw_text(
+                    &popup.points.to_string(),
+                    screen_pos.x as i32 - 20,
+                    screen_pos.y as i32,
+                    24,
+                    Color::SKYBLUE.fade(fade),
+                );
+            }
+            // Stealth: ghosts are off the minimap, only a warning while one hunts you
+            render_minimap(&mut d, &sim.maze, &sim.player, &sim.ghosts, shadow_pos, screen_width, !stealth);
+            if stealth && sim.ghosts.iter().any(|ghost| ghost.is_hunting()) {
+                d.draw_text("HUNTED", screen_width / 2 - 50, 100, 28, Color::RED);
             }
-            render_minimap(&mut d, &maze, &player, &ghosts, &texture_manager, screen_width);
         }
 
-        game_state.draw_ui(&mut d, screen_width, screen_height);
-    }
-
-    if game_state.is_game_over() {
-        let allow_buttons = if let Some(game_over_time) = game_state.game_over_time {
-            rl.get_time() < game_over_time + 5.0
-        } else { false };
-        if allow_buttons {
-            if rl.is_key_pressed(KeyboardKey::KEY_R) {
-                ghosts = spawn_ghosts(&mut rl, &thread, menu_state.num_ghosts, menu_state.difficulty);
-                game_state.reset_game(&rl);
-            }
-            if rl.is_key_pressed(KeyboardKey::KEY_M) {
-                game_state.mode = GameMode::Menu;
+        hud::draw_ui(&sim.game_state, sim.config.seed, &mut d, screen_width, screen_height, &input);
+        if sim.game_state.is_game_over() && playback.is_none() {
+            let (x, y) = (screen_width / 2 - 170, screen_height / 2 + 100);
+            match &name_entry {
+                Some(entry) => entry.draw(&mut d, x, y, &input),
+                None => draw_table(&mut d, scores.table(maze_id, sim.config.difficulty), last_rank, x + 60, y),
             }
         }
+        if demo.is_some() && sim.game_state.shows_maze() {
+            d.draw_text("DEMO - press any key", screen_width / 2 - 130, screen_height - 60, 24, Color::YELLOW);
+        }
+        if let Some(race) = race.as_ref().filter(|_| sim.game_state.shows_maze()) {
+            let elapsed = recording.as_ref().map_or(0.0, Replay::seconds);
+            let best = race.best.as_ref().map_or("--".to_string(), |best| format!("{:.2}", best.replay.seconds()));
+            d.draw_text(&format!("TIME {:.2}  BEST {}", elapsed, best), 10, screen_height - 60, 24, Color::YELLOW);
+        }
+        if let Some(active) = &playback {
+            let status = format!(
+                "REPLAY {:.0}/{:.0}s  x{}{}",
+                active.tick as f32 * SIM_DT,
+                active.replay.seconds(),
+                active.speed,
+                if active.paused { "  PAUSED" } else { "" }
+            );
+            d.draw_text(&status, 10, screen_height - 60, 20, Color::YELLOW);
+            let help = format!(
+                "{} pause  {}/{} speed  {}/{} seek  {} quit",
+                input.describe(Action::Pause),
+                input.describe(Action::MenuDown),
+                input.describe(Action::MenuUp),
+                input.describe(Action::MenuLeft),
+                input.describe(Action::MenuRight),
+                input.describe(Action::ReturnToMenu)
+            );
+            d.draw_text(&help, 10, screen_height - 35, 16, Color::LIGHTGRAY);
+        }
+    }
+
+    // Closing the window mid-game still keeps the replay
+    if let Some(replay) = recording {
+        replay.save(REPLAY_FILE);
     }
 }
 
@@ -398,11 +633,11 @@ fn render_minimap(
     d: &mut RaylibDrawHandle,
     maze: &Maze,
     player: &Player,
-    ghosts: &Vec<Ghost>,
-    _texture_manager: &TextureManager,
+    ghosts: &[Ghost],
+    shadow: Option<Vec2>,
     screen_width: i32,
+    show_ghosts: bool,
 ) {
-    let ghost_colors = [Color::RED, Color::YELLOW];
     let minimap_size = 200;
     let cell_size = minimap_size / WIDTH.max(HEIGHT) as i32;
     let offset_x = screen_width - minimap_size - 10;
@@ -441,13 +676,17 @@ fn render_minimap(
     let player_x = offset_x + grid_x * cell_size;
     let player_y = offset_y + grid_y * cell_size;
     d.draw_circle(player_x + cell_size / 2, player_y + cell_size / 2, 6.0, Color::RED);
-    for (i, ghost) in ghosts.iter().enumerate() {
+    if let Some(pos) = shadow {
+        let shadow_x = offset_x + (pos.x / CELL_SIZE as f32).round() as i32 * cell_size;
+        let shadow_y = offset_y + (pos.y / CELL_SIZE as f32).round() as i32 * cell_size;
+        d.draw_circle(shadow_x + cell_size / 2, shadow_y + cell_size / 2, 6.0, Color::YELLOW.fade(0.5));
+    }
+    for ghost in ghosts.iter().filter(|_| show_ghosts) {
         let ghost_grid_x = (ghost.pos.x / CELL_SIZE as f32).round() as i32;
         let ghost_grid_y = (ghost.pos.y / CELL_SIZE as f32).round() as i32;
         let ghost_x = offset_x + ghost_grid_x * cell_size;
         let ghost_y = offset_y + ghost_grid_y * cell_size;
-        let color = if i < ghost_colors.len() { ghost_colors[i] } else { Color::PINK };
-        d.draw_circle(ghost_x + cell_size / 2, ghost_y + cell_size / 2, 6.0, color);
+        d.draw_circle(ghost_x + cell_size / 2, ghost_y + cell_size / 2, 6.0, color(ghost.color));
     }
     let dir_x = player_x + cell_size / 2 + (player.angle.cos() * 8.0) as i32;
     let dir_y = player_y + cell_size / 2 + (player.angle.sin() * 8.0) as i32;
@@ -1,4 +1,4 @@
-use raylib::prelude::*;
+use crate::math::{Vec2, fnv1a};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Cell {
@@ -12,16 +12,30 @@ pub const CELL_SIZE: i32 = 32;
 pub const WIDTH: usize = 21;
 pub const HEIGHT: usize = 25;
 
+// Grid directions are unit steps in cells, y grows downwards
+pub type GridDir = (i32, i32);
+// Arcade tie-break order: up, left, down, right
+pub const DIRECTIONS: [GridDir; 4] = [(0, -1), (-1, 0), (0, 1), (1, 0)];
+
 pub struct Maze {
     pub grid: Vec<Vec<Cell>>,
     pellets_collected: u32,
     total_pellets: u32,
+    // Ghost house door: ghosts pass through it, the player can't
+    door: Option<(i32, i32)>,
+}
+
+impl Default for Maze {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Maze {
     pub fn new() -> Self {
         let mut grid = vec![vec![Cell::Wall; WIDTH]; HEIGHT];
         let mut total_pellets = 0;
+        let mut door = None;
         
         // Laberinto básico estilo Pac-Man
         let layout = [
@@ -62,6 +76,10 @@ impl Maze {
                             Cell::PowerPellet
                         },
                         'P' => Cell::Path, // Posición inicial del jugador
+                        '-' => {
+                            door = Some((x as i32, y as i32));
+                            Cell::Path
+                        },
                         _ => Cell::Path,
                     };
                 }
@@ -72,6 +90,7 @@ impl Maze {
             grid,
             pellets_collected: 0,
             total_pellets,
+            door,
         }
     }
 
@@ -83,6 +102,31 @@ impl Maze {
         }
     }
 
+    // Like get_cell, but takes signed coordinates so neighbours of edge cells work
+    pub fn is_wall_at(&self, x: i32, y: i32) -> bool {
+        x < 0 || y < 0 || self.get_cell(x as usize, y as usize) == Cell::Wall
+    }
+
+    pub fn is_door(&self, x: i32, y: i32) -> bool {
+        self.door == Some((x, y))
+    }
+
+    pub fn door(&self) -> Option<(i32, i32)> {
+        self.door
+    }
+
+    // Identifies the layout by its walls and door, pellets aside, so
+    // records can be kept per maze
+    pub fn layout_id(&self) -> u32 {
+        let door = self.door.map_or([0xff, 0xff], |(x, y)| [x as u8, y as u8]);
+        let cells = self.grid.iter().flatten().map(|&cell| (cell == Cell::Wall) as u8);
+        fnv1a(cells.chain(door))
+    }
+
+    pub fn blocks_player(&self, x: i32, y: i32) -> bool {
+        self.is_wall_at(x, y) || self.is_door(x, y)
+    }
+
     pub fn collect_pellet(&mut self, x: usize, y: usize) -> Option<u32> {
         if y < self.grid.len() && x < self.grid[0].len() {
             match self.grid[y][x] {
@@ -103,31 +147,36 @@ impl Maze {
         }
     }
 
+    pub fn pellets_remaining(&self) -> u32 {
+        self.total_pellets.saturating_sub(self.pellets_collected)
+    }
+
     pub fn is_level_complete(&self) -> bool {
         self.pellets_collected >= self.total_pellets
     }
 }
 
-pub fn get_pixel_color(image: &Image, x: i32, y: i32) -> Color {
-    let width = image.width as usize;
-    let height = image.height as usize;
-
-    if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
-        return Color::new(0, 0, 255, 255);
-    }
-
-    let x = x as usize;
-    let y = y as usize;
-    let data_len = width * height * 4;
-
-    unsafe {
-        let data = std::slice::from_raw_parts(image.data as *const u8, data_len);
-        let idx = (y * width + x) * 4;
+pub fn tile_of(pos: Vec2) -> (i32, i32) {
+    (
+        (pos.x / CELL_SIZE as f32).floor() as i32,
+        (pos.y / CELL_SIZE as f32).floor() as i32,
+    )
+}
 
-        if idx + 3 >= data_len {
-            return Color::new(0, 0, 255, 255);
-        }
+pub fn tile_centre(tile: (i32, i32)) -> Vec2 {
+    Vec2::new(
+        (tile.0 as f32 + 0.5) * CELL_SIZE as f32,
+        (tile.1 as f32 + 0.5) * CELL_SIZE as f32,
+    )
+}
 
-        Color::new(data[idx], data[idx + 1], data[idx + 2], data[idx + 3])
+// Closest of the four grid directions to a view angle
+pub fn dir_from_angle(angle: f32) -> GridDir {
+    let quarter = (angle / std::f32::consts::FRAC_PI_2).round() as i32;
+    match quarter.rem_euclid(4) {
+        0 => (1, 0),
+        1 => (0, 1),
+        2 => (-1, 0),
+        _ => (0, -1),
     }
 }
@@ -0,0 +1,357 @@
+use raylib::prelude::*;
+use crate::input::{Action, Binding, ControlScheme, ControlSettings, InputMap, BINDINGS_FILE};
+use pacman::level::{Difficulty, GameVariant};
+use pacman::player::MovementMode;
+use pacman::scores::HighScores;
+use crate::scoreboard::draw_table;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuScreen {
+    Main,
+    Bindings,
+    HighScores,
+}
+
+const MENU_ITEMS: usize = 9;
+// Seconds without input on the main screen before the demo starts
+const ATTRACT_DELAY: f32 = 15.0;
+// Every action plus "Reset to defaults" and "Back"
+const BINDING_ITEMS: usize = Action::ALL.len() + 2;
+
+pub struct MenuState {
+    pub num_ghosts: usize,
+    max_ghosts: usize,
+    pub difficulty: Difficulty,
+    pub variant: GameVariant,
+    selected: usize, // 0 = ghosts, 1 = difficulty, 2 = mode, 3 = controls, 4 = movement, 5 = sensitivity, 6 = key bindings, 7 = high scores, 8 = start
+    screen: MenuScreen,
+    binding_selected: usize,
+    waiting_for_key: bool,
+    idle_time: f32,
+}
+
+impl MenuState {
+    pub fn new(max_ghosts: usize) -> Self {
+        let max_ghosts = max_ghosts.max(1);
+        MenuState {
+            num_ghosts: max_ghosts.min(2),
+            max_ghosts,
+            difficulty: Difficulty::Medium,
+            variant: GameVariant::Classic,
+            selected: 0,
+            screen: MenuScreen::Main,
+            binding_selected: 0,
+            waiting_for_key: false,
+            idle_time: 0.0,
+        }
+    }
+
+    // Returns true when the player asked to start a game
+    pub fn update(&mut self, rl: &mut RaylibHandle, input: &mut InputMap, controls: &mut ControlSettings) -> bool {
+        match self.screen {
+            MenuScreen::Main => self.update_main(rl, input, controls),
+            MenuScreen::Bindings => {
+                self.update_bindings(rl, input);
+                false
+            }
+            MenuScreen::HighScores => {
+                self.update_high_scores(rl, input);
+                false
+            }
+        }
+    }
+
+    // Returns true when the menu has been left alone long enough to start
+    // the attract-mode demo
+    pub fn update_idle(&mut self, rl: &RaylibHandle, input: &InputMap, dt: f32) -> bool {
+        if self.screen != MenuScreen::Main || input.any_pressed(rl) {
+            self.idle_time = 0.0;
+            return false;
+        }
+        self.idle_time += dt;
+        if self.idle_time < ATTRACT_DELAY {
+            return false;
+        }
+        self.idle_time = 0.0;
+        true
+    }
+
+    fn change_difficulty(&mut self, left: bool, right: bool) {
+        if left {
+            self.difficulty = match self.difficulty {
+                Difficulty::Easy => Difficulty::Hard,
+                Difficulty::Medium => Difficulty::Easy,
+                Difficulty::Hard => Difficulty::Medium,
+            };
+        }
+        if right {
+            self.difficulty = match self.difficulty {
+                Difficulty::Easy => Difficulty::Medium,
+                Difficulty::Medium => Difficulty::Hard,
+                Difficulty::Hard => Difficulty::Easy,
+            };
+        }
+    }
+
+    fn update_main(&mut self, rl: &RaylibHandle, input: &InputMap, controls: &mut ControlSettings) -> bool {
+        if input.is_pressed(rl, Action::MenuDown) {
+            self.selected = (self.selected + 1) % MENU_ITEMS;
+        }
+        if input.is_pressed(rl, Action::MenuUp) {
+            self.selected = (self.selected + MENU_ITEMS - 1) % MENU_ITEMS;
+        }
+        let left = input.is_pressed(rl, Action::MenuLeft);
+        let right = input.is_pressed(rl, Action::MenuRight);
+        match self.selected {
+            0 => {
+                if left {
+                    self.num_ghosts = (self.num_ghosts - 1).max(1);
+                }
+                if right {
+                    self.num_ghosts = (self.num_ghosts + 1).min(self.max_ghosts);
+                }
+            }
+            1 => self.change_difficulty(left, right),
+            2 if left => {
+                self.variant = match self.variant {
+                    GameVariant::Classic => GameVariant::TimeAttack,
+                    GameVariant::Stealth => GameVariant::Classic,
+                    GameVariant::TimeAttack => GameVariant::Stealth,
+                };
+            }
+            2 if right => {
+                self.variant = match self.variant {
+                    GameVariant::Classic => GameVariant::Stealth,
+                    GameVariant::Stealth => GameVariant::TimeAttack,
+                    GameVariant::TimeAttack => GameVariant::Classic,
+                };
+            }
+            3 if left || right => {
+                controls.scheme = match controls.scheme {
+                    ControlScheme::Classic => ControlScheme::Mouse,
+                    ControlScheme::Mouse => ControlScheme::Classic,
+                };
+            }
+            4 if left || right => {
+                controls.movement = match controls.movement {
+                    MovementMode::Free => MovementMode::Grid,
+                    MovementMode::Grid => MovementMode::Free,
+                };
+            }
+            5 => {
+                if left {
+                    controls.mouse_sensitivity = (controls.mouse_sensitivity - 0.25).max(0.25);
+                }
+                if right {
+                    controls.mouse_sensitivity = (controls.mouse_sensitivity + 0.25).min(3.0);
+                }
+            }
+            _ => {}
+        }
+
+        if input.is_pressed(rl, Action::Confirm) {
+            if self.selected == 6 {
+                self.screen = MenuScreen::Bindings;
+                self.binding_selected = 0;
+                return false;
+            }
+            if self.selected == 7 {
+                self.screen = MenuScreen::HighScores;
+                return false;
+            }
+            return true;
+        }
+        false
+    }
+
+    // Left/right flips through the difficulties
+    fn update_high_scores(&mut self, rl: &RaylibHandle, input: &InputMap) {
+        let left = input.is_pressed(rl, Action::MenuLeft);
+        let right = input.is_pressed(rl, Action::MenuRight);
+        self.change_difficulty(left, right);
+        if input.is_pressed(rl, Action::Back) || input.is_pressed(rl, Action::Confirm) {
+            self.screen = MenuScreen::Main;
+        }
+    }
+
+    fn update_bindings(&mut self, rl: &mut RaylibHandle, input: &mut InputMap) {
+        if self.waiting_for_key {
+            // ESC cancels instead of quitting while a key is awaited
+            if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                self.stop_waiting(rl);
+                return;
+            }
+            let pressed = rl
+                .get_key_pressed()
+                .map(Binding::Key)
+                .or_else(|| input.pad_button_pressed().map(Binding::Pad));
+            if let Some(binding) = pressed {
+                let action = Action::ALL[self.binding_selected];
+                input.rebind(action, binding);
+                self.stop_waiting(rl);
+                save_bindings(input);
+            }
+            return;
+        }
+
+        if input.is_pressed(rl, Action::MenuDown) {
+            self.binding_selected = (self.binding_selected + 1) % BINDING_ITEMS;
+        }
+        if input.is_pressed(rl, Action::MenuUp) {
+            self.binding_selected = (self.binding_selected + BINDING_ITEMS - 1) % BINDING_ITEMS;
+        }
+        if input.is_pressed(rl, Action::Back) {
+            self.screen = MenuScreen::Main;
+            return;
+        }
+        if input.is_pressed(rl, Action::Confirm) {
+            if self.binding_selected < Action::ALL.len() {
+                self.waiting_for_key = true;
+                rl.set_exit_key(None);
+            } else if self.binding_selected == Action::ALL.len() {
+                input.reset();
+                save_bindings(input);
+            } else {
+                self.screen = MenuScreen::Main;
+            }
+        }
+    }
+
+    fn stop_waiting(&mut self, rl: &mut RaylibHandle) {
+        self.waiting_for_key = false;
+        rl.set_exit_key(Some(KeyboardKey::KEY_ESCAPE));
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, input: &InputMap, controls: &ControlSettings, scores: &HighScores, maze_id: u32) {
+        match self.screen {
+            MenuScreen::Main => self.draw_main(d, input, controls),
+            MenuScreen::Bindings => self.draw_bindings(d, input),
+            MenuScreen::HighScores => self.draw_high_scores(d, input, scores, maze_id),
+        }
+    }
+
+    fn draw_high_scores(&self, d: &mut RaylibDrawHandle, input: &InputMap, scores: &HighScores, maze_id: u32) {
+        d.draw_text("HIGH SCORES", 270, 60, 36, Color::YELLOW);
+        d.draw_text(&format!("< {} >", difficulty_label(self.difficulty)), 330, 110, 24, Color::ORANGE);
+        draw_table(d, scores.table(maze_id, self.difficulty), None, 230, 170);
+        let help = format!(
+            "{}/{} difficulty, {} to go back",
+            input.describe(Action::MenuLeft),
+            input.describe(Action::MenuRight),
+            input.describe(Action::Back),
+        );
+        d.draw_text(&help, 100, 520, 18, Color::LIGHTGRAY);
+    }
+
+    fn draw_main(&self, d: &mut RaylibDrawHandle, input: &InputMap, controls: &ControlSettings) {
+        d.draw_text("PAC-MAN 3D", 260, 100, 40, Color::YELLOW);
+        let ghost_str = format!("Number of Ghosts: {}", self.num_ghosts);
+        let diff_str = format!("Difficulty: {}", difficulty_label(self.difficulty));
+        let variant_str = format!("Mode: {}", match self.variant {
+            GameVariant::Classic => "Classic",
+            GameVariant::Stealth => "Stealth",
+            GameVariant::TimeAttack => "Time Attack",
+        });
+        let controls_str = format!("Controls: {}", match controls.scheme {
+            ControlScheme::Classic => "Classic",
+            ControlScheme::Mouse => "Mouse Look",
+        });
+        let movement_str = format!("Movement: {}", match controls.movement {
+            MovementMode::Free => "Free",
+            MovementMode::Grid => "Grid-locked",
+        });
+        let sens_str = format!("Mouse Sensitivity: {:.2}", controls.mouse_sensitivity);
+        let menu_items = [
+            ghost_str.as_str(),
+            diff_str.as_str(),
+            variant_str.as_str(),
+            controls_str.as_str(),
+            movement_str.as_str(),
+            sens_str.as_str(),
+            "Key Bindings",
+            "High Scores",
+            "Start Game",
+        ];
+        for (i, item) in menu_items.iter().enumerate() {
+            let color = if i == self.selected { Color::GREEN } else { Color::WHITE };
+            d.draw_text(item, 200, 200 + i as i32 * 40, 30, color);
+        }
+        let help = format!(
+            "Use {}/{} to select, {}/{} to change, {} to start",
+            input.describe(Action::MenuUp),
+            input.describe(Action::MenuDown),
+            input.describe(Action::MenuLeft),
+            input.describe(Action::MenuRight),
+            input.describe(Action::Confirm),
+        );
+        d.draw_text(&help, 100, 570, 18, Color::LIGHTGRAY);
+        let scheme_help = match (controls.movement, controls.scheme) {
+            (MovementMode::Grid, _) => format!(
+                "{} go, {} reverse, {}/{} queue a turn",
+                input.describe(Action::MoveForward),
+                input.describe(Action::MoveBackward),
+                input.describe(Action::TurnLeft),
+                input.describe(Action::TurnRight),
+            ),
+            (MovementMode::Free, ControlScheme::Classic) => format!(
+                "{}/{} move, {}/{} turn, {}/{} strafe",
+                input.describe(Action::MoveForward),
+                input.describe(Action::MoveBackward),
+                input.describe(Action::TurnLeft),
+                input.describe(Action::TurnRight),
+                input.describe(Action::StrafeLeft),
+                input.describe(Action::StrafeRight),
+            ),
+            (MovementMode::Free, ControlScheme::Mouse) => format!(
+                "{}/{} move, {}/{} strafe, mouse to look",
+                input.describe(Action::MoveForward),
+                input.describe(Action::MoveBackward),
+                input.describe(Action::TurnLeft),
+                input.describe(Action::TurnRight),
+            ),
+        };
+        d.draw_text(&scheme_help, 100, 600, 18, Color::LIGHTGRAY);
+    }
+
+    fn draw_bindings(&self, d: &mut RaylibDrawHandle, input: &InputMap) {
+        d.draw_text("KEY BINDINGS", 250, 40, 32, Color::YELLOW);
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let y = 100 + i as i32 * 30;
+            let selected = i == self.binding_selected;
+            let color = if selected { Color::GREEN } else { Color::WHITE };
+            let keys = if selected && self.waiting_for_key {
+                "press a key or button (ESC cancels)".to_string()
+            } else {
+                input.describe(*action)
+            };
+            d.draw_text(action.label(), 150, y, 20, color);
+            d.draw_text(&keys, 450, y, 20, color);
+        }
+        let extra_y = 100 + Action::ALL.len() as i32 * 30 + 10;
+        let extras = ["Reset to defaults", "Back"];
+        for (i, item) in extras.iter().enumerate() {
+            let color = if Action::ALL.len() + i == self.binding_selected { Color::GREEN } else { Color::WHITE };
+            d.draw_text(item, 150, extra_y + i as i32 * 30, 20, color);
+        }
+        let help = format!(
+            "{} to rebind, {} to go back (ESC quits the game)",
+            input.describe(Action::Confirm),
+            input.describe(Action::Back),
+        );
+        d.draw_text(&help, 100, 760, 18, Color::LIGHTGRAY);
+    }
+}
+
+fn difficulty_label(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "Easy",
+        Difficulty::Medium => "Medium",
+        Difficulty::Hard => "Hard",
+    }
+}
+
+fn save_bindings(input: &InputMap) {
+    if let Err(e) = input.save(BINDINGS_FILE) {
+        println!("Error al guardar {}: {}", BINDINGS_FILE, e);
+    }
+}
@@ -1,73 +1,292 @@
-use raylib::prelude::*;
-use crate::maze::{Maze, Cell, CELL_SIZE};
+use crate::math::Vec2;
+use crate::maze::{Maze, GridDir, CELL_SIZE, dir_from_angle, tile_of, tile_centre};
 use crate::game_state::GameState;
 
+// Pixels per second; ghost speeds are fractions of it
+pub const BASE_SPEED: f32 = 150.0;
+const POWER_SPEED_MULTIPLIER: f32 = 1.5;
+// Radians per second
+const ROTATION_SPEED: f32 = 3.0;
+// Pixels; has to stay below half a cell so the player fits in corridors
+pub const PLAYER_RADIUS: f32 = 10.0;
+// Extra passes let corner pushes settle when two walls overlap the circle
+const COLLISION_ITERATIONS: usize = 3;
+// Radians per second the camera swings through on a grid-locked turn
+const GRID_TURN_SPEED: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MovementMode {
+    Free, // move anywhere, walls slide
+    Grid, // arcade style: corridor centrelines, buffered 90 degree turns
+}
+
+// Grid-locked movement state
+pub struct GridMotion {
+    heading: Option<GridDir>, // None while standing still
+    queued: Option<GridDir>,  // turn waiting for the next intersection
+    target_angle: f32,        // where the camera is rotating to
+    turn_held: bool,          // turns queue on press, not while held
+}
+
+impl GridMotion {
+    pub fn heading(&self) -> Option<GridDir> {
+        self.heading
+    }
+
+    // The way a press of forward would set off
+    pub fn facing(&self) -> GridDir {
+        self.heading.unwrap_or_else(|| dir_from_angle(self.target_angle))
+    }
+}
+
 pub struct Player {
-    pub pos: Vector2,
+    pub pos: Vec2,
     pub angle: f32,
     pub speed: f32,
     pub rotation_speed: f32,
+    pub radius: f32,
+    pub movement: MovementMode,
+    pub grid: GridMotion,
+    // State at the start of the current simulation tick, for interpolation
+    pub prev_pos: Vec2,
+    pub prev_angle: f32,
 }
 
 impl Player {
-    pub fn new(grid_x: usize, grid_y: usize) -> Self {
+    pub fn new(grid_x: usize, grid_y: usize, movement: MovementMode) -> Self {
         let x = (grid_x as i32 * CELL_SIZE + CELL_SIZE / 2) as f32;
         let y = (grid_y as i32 * CELL_SIZE + CELL_SIZE / 2) as f32;
         Self {
-            pos: Vector2::new(x, y),
+            pos: Vec2::new(x, y),
             angle: 0.0,
-            speed: 2.5,
-            rotation_speed: 0.05,
+            speed: BASE_SPEED,
+            rotation_speed: ROTATION_SPEED,
+            radius: PLAYER_RADIUS,
+            movement,
+            grid: GridMotion {
+                heading: None,
+                queued: None,
+                target_angle: 0.0,
+                turn_held: false,
+            },
+            prev_pos: Vec2::new(x, y),
+            prev_angle: 0.0,
         }
     }
+
+    pub fn store_previous(&mut self) {
+        self.prev_pos = self.pos;
+        self.prev_angle = self.angle;
+    }
+
+    // Position and view angle blended between the last two ticks
+    pub fn interpolated(&self, alpha: f32) -> (Vec2, f32) {
+        (
+            self.prev_pos.lerp(self.pos, alpha),
+            self.prev_angle + (self.angle - self.prev_angle) * alpha,
+        )
+    }
+}
+
+// What the player asked for during one frame, in simulation terms
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerInput {
+    pub forward: f32, // -1.0 (back) to 1.0 (forward)
+    pub strafe: f32,  // -1.0 (left) to 1.0 (right)
+    pub turn: f32,    // -1.0 (left) to 1.0 (right), scaled by the rotation speed
+    pub look: f32,    // radians from mouse look, applied once
 }
 
-pub fn process_events(player: &mut Player, maze: &Maze, rl: &RaylibHandle, game_state: &GameState) {
+impl PlayerInput {
+    // Analog axes rounded to 1/127 steps, which is what replays store
+    pub fn quantized(&self) -> PlayerInput {
+        let axis = |value: f32| (value.clamp(-1.0, 1.0) * 127.0).round() / 127.0;
+        PlayerInput {
+            forward: axis(self.forward),
+            strafe: axis(self.strafe),
+            turn: axis(self.turn),
+            look: self.look,
+        }
+    }
+}
+
+pub fn update_player(player: &mut Player, cmd: &PlayerInput, maze: &Maze, game_state: &GameState, dt: f32) {
     // Set speed based on power mode
     if game_state.power_mode_active {
-        player.speed = 2.5 * 1.5;
+        player.speed = BASE_SPEED * POWER_SPEED_MULTIPLIER;
     } else {
-        player.speed = 2.5;
+        player.speed = BASE_SPEED;
     }
-    let mut dir = Vector2::new(0.0, 0.0);
 
-    if rl.is_key_down(KeyboardKey::KEY_D) {
-        player.angle += player.rotation_speed;
+    if player.movement == MovementMode::Grid {
+        update_grid_player(player, cmd, maze, dt);
+        return;
     }
-    if rl.is_key_down(KeyboardKey::KEY_A) {
-        player.angle -= player.rotation_speed;
+
+    player.angle += cmd.look + cmd.turn * player.rotation_speed * dt;
+
+    // Right-hand side of the view direction (y grows downwards)
+    let mut dir = Vec2::new(
+        player.angle.cos() * cmd.forward - player.angle.sin() * cmd.strafe,
+        player.angle.sin() * cmd.forward + player.angle.cos() * cmd.strafe,
+    );
+
+    if dir.length() > 0.0 {
+        let step = player.speed * dir.length().min(1.0) * dt;
+        dir = dir.normalized(); 
+        player.pos = move_with_collision(player.pos, dir * step, player.radius, maze);
     }
+}
 
-    if rl.is_key_down(KeyboardKey::KEY_W) {
-        dir.x += player.angle.cos();
-        dir.y += player.angle.sin();
+fn update_grid_player(player: &mut Player, cmd: &PlayerInput, maze: &Maze, dt: f32) {
+    let grid = &mut player.grid;
+    let facing = grid.facing();
+
+    // Strafe keys turn as well, so the mouse scheme's turn-to-strafe keys still work
+    let turn_input = cmd.turn + cmd.strafe;
+    let turning = turn_input.abs() > 0.5;
+    if turning && !grid.turn_held {
+        grid.queued = Some(if turn_input > 0.0 { turn_right(facing) } else { turn_left(facing) });
     }
-    if rl.is_key_down(KeyboardKey::KEY_S) {
-        dir.x -= player.angle.cos();
-        dir.y -= player.angle.sin();
+    grid.turn_held = turning;
+
+    if cmd.forward < -0.5 {
+        // Reversing never has to wait for an intersection
+        if let Some(heading) = grid.heading {
+            grid.heading = Some((-heading.0, -heading.1));
+            grid.queued = None;
+            grid.target_angle += std::f32::consts::PI;
+        }
+    } else if cmd.forward > 0.5 && grid.heading.is_none() {
+        grid.heading = Some(facing);
     }
 
-    if dir.length() > 0.0 {
-        dir = dir.normalized(); 
-        let next_x = player.pos.x + dir.x * player.speed;
-        let next_y = player.pos.y + dir.y * player.speed;
+    let cell = CELL_SIZE as f32;
+    let mut remaining = player.speed * dt;
+    // At most one cell centre can be crossed per tick, the loop only guards
+    // against very long ticks
+    while remaining > 0.0 {
+        let Some(heading) = grid.heading else { break };
+        let (cx, cy) = tile_of(player.pos);
+        let centre = tile_centre((cx, cy));
 
-        if !check_collision(next_x, player.pos.y, maze) {
-            player.pos.x = next_x;
+        // Stay on the corridor centreline
+        if heading.0 != 0 {
+            player.pos.y = centre.y;
+        } else {
+            player.pos.x = centre.x;
         }
-        if !check_collision(player.pos.x, next_y, maze) {
-            player.pos.y = next_y;
+
+        let to_centre = (centre.x - player.pos.x) * heading.0 as f32 + (centre.y - player.pos.y) * heading.1 as f32;
+        if to_centre >= 0.0 && to_centre <= remaining {
+            // Reaching the centre of a cell: take the buffered turn if the way is open
+            player.pos = centre;
+            remaining -= to_centre;
+            if let Some(queued) = grid.queued.filter(|&dir| !maze.blocks_player(cx + dir.0, cy + dir.1)) {
+                grid.target_angle += if queued == turn_right(heading) {
+                    std::f32::consts::FRAC_PI_2
+                } else if queued == turn_left(heading) {
+                    -std::f32::consts::FRAC_PI_2
+                } else {
+                    0.0
+                };
+                grid.heading = Some(queued);
+                grid.queued = None;
+            } else if maze.blocks_player(cx + heading.0, cy + heading.1) {
+                grid.heading = None;
+            }
+            if remaining <= f32::EPSILON {
+                break;
+            }
+            // Leave the centre; half a cell later the next pass finds the next one ahead
+            let Some(heading) = grid.heading else { break };
+            let step = remaining.min(cell / 2.0);
+            player.pos += Vec2::new(heading.0 as f32, heading.1 as f32) * step;
+            remaining -= step;
+        } else {
+            // Up to this cell's centre, or the next one if this one is behind us
+            let step = if to_centre > 0.0 { to_centre } else { cell + to_centre };
+            let step = step.min(remaining);
+            player.pos += Vec2::new(heading.0 as f32, heading.1 as f32) * step;
+            remaining -= step;
         }
     }
+
+    // Swing the camera towards the heading instead of snapping 90 degrees
+    let diff = grid.target_angle - player.angle;
+    let max_turn = GRID_TURN_SPEED * dt;
+    player.angle += diff.clamp(-max_turn, max_turn);
+}
+
+fn turn_right(dir: GridDir) -> GridDir {
+    (-dir.1, dir.0)
+}
+
+fn turn_left(dir: GridDir) -> GridDir {
+    (dir.1, -dir.0)
 }
 
-pub fn check_collision(x: f32, y: f32, maze: &Maze) -> bool {
-    let grid_x = (x / CELL_SIZE as f32) as usize;
-    let grid_y = (y / CELL_SIZE as f32) as usize;
+// Moves a circle by delta, pushing it out of any wall it touches so it
+// slides along walls instead of stopping dead
+pub fn move_with_collision(pos: Vec2, delta: Vec2, radius: f32, maze: &Maze) -> Vec2 {
+    // Sub-step so a fast move can never skip past a wall
+    let max_step = radius * 0.5;
+    let steps = (delta.length() / max_step).ceil().max(1.0) as i32;
+    let step = delta / steps as f32;
 
-    if grid_y >= maze.grid.len() || grid_x >= maze.grid[0].len() {
-        return true;
+    let mut pos = pos;
+    for _ in 0..steps {
+        pos = resolve_collision(pos + step, radius, maze);
     }
+    pos
+}
 
-    maze.grid[grid_y][grid_x] == Cell::Wall
+pub fn resolve_collision(pos: Vec2, radius: f32, maze: &Maze) -> Vec2 {
+    let mut pos = pos;
+    let cell = CELL_SIZE as f32;
+    for _ in 0..COLLISION_ITERATIONS {
+        let mut pushed = false;
+        let cx = (pos.x / cell).floor() as i32;
+        let cy = (pos.y / cell).floor() as i32;
+        for gy in cy - 1..=cy + 1 {
+            for gx in cx - 1..=cx + 1 {
+                if !maze.blocks_player(gx, gy) {
+                    continue;
+                }
+                // Closest point of the wall cell to the circle centre
+                let min_x = gx as f32 * cell;
+                let min_y = gy as f32 * cell;
+                let closest = Vec2::new(
+                    pos.x.clamp(min_x, min_x + cell),
+                    pos.y.clamp(min_y, min_y + cell),
+                );
+                let offset = pos - closest;
+                let dist = offset.length();
+                if dist >= radius {
+                    continue;
+                }
+                if dist > f32::EPSILON {
+                    pos += offset / dist * (radius - dist);
+                } else {
+                    // Centre ended up inside the wall: leave through the nearest side
+                    let exits = [
+                        (pos.x - min_x, Vec2::new(-1.0, 0.0)),
+                        (min_x + cell - pos.x, Vec2::new(1.0, 0.0)),
+                        (pos.y - min_y, Vec2::new(0.0, -1.0)),
+                        (min_y + cell - pos.y, Vec2::new(0.0, 1.0)),
+                    ];
+                    let (depth, normal) = exits
+                        .iter()
+                        .copied()
+                        .fold(exits[0], |best, exit| if exit.0 < best.0 { exit } else { best });
+                    pos += normal * (depth + radius);
+                }
+                pushed = true;
+            }
+        }
+        if !pushed {
+            break;
+        }
+    }
+    pos
 }
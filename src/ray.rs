@@ -1,4 +1,4 @@
-use raylib::math::Vector2;
+use crate::math::Vec2;
 use crate::maze::{Maze, Cell, CELL_SIZE};
 
 pub struct Intersect {
@@ -8,13 +8,13 @@ pub struct Intersect {
     pub texture_coord: f32,
 }
 
-pub fn cast_ray(origin: Vector2, angle: f32, maze: &Maze) -> Intersect {
+pub fn cast_ray(origin: Vec2, angle: f32, maze: &Maze) -> Intersect {
     let mut distance = 0.0;
     let step = 0.5;
     let mut wall_x = 0;
     let mut wall_y = 0;
-    let mut target_x = 0.0;
-    let mut target_y = 0.0;
+    let mut target_x;
+    let mut target_y;
 
     loop {
         distance += step;
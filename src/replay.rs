@@ -0,0 +1,405 @@
+use std::fs;
+use crate::level::{Difficulty, GameVariant, GHOSTS_FILE};
+use crate::player::{MovementMode, PlayerInput};
+use crate::scripting::SCRIPT_FILE;
+use crate::sim::{SimConfig, Simulation, SIM_DT};
+
+pub const REPLAY_FILE: &str = "last.replay";
+const MAGIC: &[u8; 4] = b"PMRP";
+// Bumped whenever the file layout changes
+pub const REPLAY_VERSION: u16 = 2;
+// Game version written into replays; the same inputs only play out the
+// same way on the same game logic
+const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub(crate) const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+pub(crate) const VARIANTS: [GameVariant; 3] = [GameVariant::Classic, GameVariant::Stealth, GameVariant::TimeAttack];
+pub(crate) const MOVEMENTS: [MovementMode; 2] = [MovementMode::Free, MovementMode::Grid];
+
+// Everything needed to play a game again: the settings it started with
+// (seed included), the data files it was played with and the input of
+// every tick. Inputs are stored quantized, the same way the simulation
+// reads them.
+#[derive(Clone)]
+pub struct Replay {
+    pub config: SimConfig,
+    pub game_version: String,
+    // Simulation::data_hashes of the game: ghosts file and script
+    pub data_hashes: (u32, u32),
+    pub inputs: Vec<PlayerInput>,
+    // Ticks before which the player moved on from a cleared level
+    pub next_levels: Vec<u32>,
+}
+
+impl Replay {
+    // A recording of the game `sim` has just started
+    pub fn new(sim: &Simulation) -> Self {
+        Replay {
+            config: sim.config,
+            game_version: GAME_VERSION.to_string(),
+            data_hashes: sim.data_hashes(),
+            inputs: Vec::new(),
+            next_levels: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, input: &PlayerInput) {
+        self.inputs.push(input.quantized());
+    }
+
+    pub fn record_next_level(&mut self) {
+        self.next_levels.push(self.inputs.len() as u32);
+    }
+
+    pub fn seconds(&self) -> f32 {
+        self.inputs.len() as f32 * SIM_DT
+    }
+
+    // Header, then runs of identical inputs: a keyboard player holds the
+    // same keys for many ticks, so most games shrink to a few KB
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.push(self.game_version.len() as u8);
+        bytes.extend_from_slice(self.game_version.as_bytes());
+        bytes.extend_from_slice(&self.data_hashes.0.to_le_bytes());
+        bytes.extend_from_slice(&self.data_hashes.1.to_le_bytes());
+        bytes.extend_from_slice(&self.config.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.config.start_level.to_le_bytes());
+        bytes.push(code(&DIFFICULTIES, self.config.difficulty));
+        bytes.push(code(&VARIANTS, self.config.variant));
+        bytes.push(code(&MOVEMENTS, self.config.movement));
+        bytes.extend_from_slice(&(self.config.num_ghosts.min(u32::MAX as usize) as u32).to_le_bytes());
+
+        bytes.extend_from_slice(&(self.next_levels.len() as u32).to_le_bytes());
+        for tick in self.next_levels.iter() {
+            bytes.extend_from_slice(&tick.to_le_bytes());
+        }
+
+        let mut runs: Vec<(u16, [i8; 3], f32)> = Vec::new();
+        for input in self.inputs.iter() {
+            let axes = [axis_code(input.forward), axis_code(input.strafe), axis_code(input.turn)];
+            match runs.last_mut() {
+                Some((count, last_axes, look)) if *last_axes == axes && *look == input.look && *count < u16::MAX => {
+                    *count += 1;
+                }
+                _ => runs.push((1, axes, input.look)),
+            }
+        }
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (count, axes, look) in runs {
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.extend(axes.iter().map(|&axis| axis as u8));
+            bytes.extend_from_slice(&look.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4) != Some(MAGIC.as_slice()) {
+            return Err("no es un archivo de repetición".to_string());
+        }
+        let damaged = || "archivo dañado".to_string();
+        let version = reader.u16().ok_or_else(damaged)?;
+        if version != REPLAY_VERSION {
+            return Err(format!("versión de formato {} no soportada (se esperaba {})", version, REPLAY_VERSION));
+        }
+        let version_len = reader.u8().ok_or_else(damaged)? as usize;
+        let game_version = String::from_utf8_lossy(reader.take(version_len).ok_or_else(damaged)?).to_string();
+        let data_hashes = (reader.u32().ok_or_else(damaged)?, reader.u32().ok_or_else(damaged)?);
+        let config = SimConfig {
+            seed: reader.u64().ok_or_else(damaged)?,
+            start_level: reader.u32().ok_or_else(damaged)?,
+            difficulty: from_code(&DIFFICULTIES, reader.u8()).ok_or_else(damaged)?,
+            variant: from_code(&VARIANTS, reader.u8()).ok_or_else(damaged)?,
+            movement: from_code(&MOVEMENTS, reader.u8()).ok_or_else(damaged)?,
+            // usize::MAX (all of them) was clamped on the way out
+            num_ghosts: match reader.u32().ok_or_else(damaged)? {
+                u32::MAX => usize::MAX,
+                count => count as usize,
+            },
+        };
+
+        let count = reader.u32().ok_or_else(damaged)?;
+        let mut next_levels = Vec::new();
+        for _ in 0..count {
+            next_levels.push(reader.u32().ok_or_else(damaged)?);
+        }
+
+        let runs = reader.u32().ok_or_else(damaged)?;
+        let mut inputs = Vec::new();
+        for _ in 0..runs {
+            let count = reader.u16().ok_or_else(damaged)?;
+            let axes = reader.take(3).ok_or_else(damaged)?;
+            let input = PlayerInput {
+                forward: axis_value(axes[0] as i8),
+                strafe: axis_value(axes[1] as i8),
+                turn: axis_value(axes[2] as i8),
+                look: reader.f32().ok_or_else(damaged)?,
+            };
+            inputs.extend(std::iter::repeat_n(input, count as usize));
+        }
+        Ok(Replay { config, game_version, data_hashes, inputs, next_levels })
+    }
+
+    pub fn save(&self, path: &str) {
+        match fs::write(path, self.to_bytes()) {
+            Ok(()) => println!("Repetición guardada en {} ({:.0} s, semilla {})", path, self.seconds(), self.config.seed),
+            Err(e) => println!("No se pudo guardar la repetición en {}: {}", path, e),
+        }
+    }
+
+    // Warns when the ghosts file or the script changed since the recording,
+    // in which case the game will likely play out differently
+    pub fn check_data(&self, sim: &Simulation, path: &str) -> bool {
+        let (ghosts, script) = sim.data_hashes();
+        let mut matches = true;
+        if self.data_hashes.0 != ghosts {
+            println!("{}: {} ha cambiado desde la grabación; puede no reproducirse igual", path, GHOSTS_FILE);
+            matches = false;
+        }
+        if self.data_hashes.1 != script {
+            println!("{}: {} ha cambiado desde la grabación; puede no reproducirse igual", path, SCRIPT_FILE);
+            matches = false;
+        }
+        matches
+    }
+
+    pub fn load(path: &str) -> Option<Replay> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                println!("No se pudo leer la repetición {}: {}", path, e);
+                return None;
+            }
+        };
+        match Replay::from_bytes(&bytes) {
+            Ok(replay) => {
+                if replay.game_version != GAME_VERSION {
+                    println!(
+                        "{} se grabó con la versión {} del juego; puede no reproducirse igual en la {}",
+                        path, replay.game_version, GAME_VERSION
+                    );
+                }
+                Some(replay)
+            }
+            Err(e) => {
+                println!("Repetición {} inválida: {}", path, e);
+                None
+            }
+        }
+    }
+}
+
+fn code<T: PartialEq>(values: &[T], value: T) -> u8 {
+    values.iter().position(|v| *v == value).unwrap_or(0) as u8
+}
+
+fn from_code<T: Copy>(values: &[T], code: Option<u8>) -> Option<T> {
+    values.get(code? as usize).copied()
+}
+
+// Inverse of PlayerInput::quantized for the three analog axes
+fn axis_code(value: f32) -> i8 {
+    (value.clamp(-1.0, 1.0) * 127.0).round() as i8
+}
+
+fn axis_value(code: i8) -> f32 {
+    code as f32 / 127.0
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).and_then(|b| b.try_into().ok()).map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8).and_then(|b| b.try_into().ok()).map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.take(4).and_then(|b| b.try_into().ok()).map(f32::from_le_bytes)
+    }
+}
+
+// Drives a simulation from a replay instead of a player. Seeking backwards
+// starts the game over and re-simulates up to the wanted tick.
+pub struct Playback {
+    pub replay: Replay,
+    pub tick: usize,
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback {
+            replay,
+            tick: 0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+
+    pub fn restart(&mut self, sim: &mut Simulation) {
+        *sim = sim.fresh();
+        sim.start_game(self.replay.config);
+        self.tick = 0;
+    }
+
+    pub fn step(&mut self, sim: &mut Simulation) {
+        let Some(input) = self.replay.inputs.get(self.tick) else {
+            return;
+        };
+        if self.replay.next_levels.contains(&(self.tick as u32)) {
+            sim.next_level();
+        }
+        sim.tick(input);
+        self.tick += 1;
+    }
+
+    pub fn seek(&mut self, sim: &mut Simulation, tick: usize) {
+        let tick = tick.min(self.replay.inputs.len());
+        if tick < self.tick {
+            self.restart(sim);
+        }
+        while self.tick < tick {
+            self.step(sim);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::{default_ghost_config, parse_ghost_config};
+    use crate::math::Vec2;
+
+    fn sample_replay() -> Replay {
+        let mut sim = Simulation::new(default_ghost_config(), None);
+        sim.start_game(SimConfig {
+            difficulty: Difficulty::Hard,
+            variant: GameVariant::Stealth,
+            num_ghosts: usize::MAX,
+            movement: MovementMode::Grid,
+            seed: 0xDEAD_BEEF_1234,
+            start_level: 3,
+        });
+        let mut replay = Replay::new(&sim);
+        replay.data_hashes = (0x1234_5678, 0x9abc_def0);
+        let held = PlayerInput { forward: 1.0, ..PlayerInput::default() };
+        // Longer than one run can hold
+        for _ in 0..u16::MAX as usize + 10 {
+            replay.record(&held);
+        }
+        replay.record_next_level();
+        replay.record(&PlayerInput { forward: -0.5, strafe: 0.25, turn: -1.0, look: 0.125 });
+        replay.record(&PlayerInput::default());
+        replay.record_next_level();
+        replay
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let replay = sample_replay();
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.config, replay.config);
+        assert_eq!(loaded.config.num_ghosts, usize::MAX);
+        assert_eq!(loaded.game_version, replay.game_version);
+        assert_eq!(loaded.data_hashes, replay.data_hashes);
+        assert_eq!(loaded.next_levels, replay.next_levels);
+        assert_eq!(loaded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn rejects_damaged_files() {
+        let bytes = sample_replay().to_bytes();
+        for len in 0..bytes.len() {
+            assert!(Replay::from_bytes(&bytes[..len]).is_err(), "truncated to {} bytes", len);
+        }
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(Replay::from_bytes(&bad_magic).is_err());
+
+        let mut bad_version = bytes.clone();
+        bad_version[4..6].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        assert!(Replay::from_bytes(&bad_version).is_err());
+    }
+
+    #[test]
+    fn notices_changed_data_files() {
+        let sim = Simulation::new(default_ghost_config(), None);
+        let mut replay = Replay::new(&sim);
+        assert!(replay.check_data(&sim, REPLAY_FILE));
+        replay.data_hashes.0 ^= 1;
+        assert!(!replay.check_data(&sim, REPLAY_FILE));
+
+        let retuned = Simulation::new(parse_ghost_config("[blinky]\nspawn = 9, 7\n"), None);
+        assert!(!Replay::new(&sim).check_data(&retuned, REPLAY_FILE));
+    }
+
+    fn snapshot(sim: &Simulation) -> (Vec2, Vec<Vec2>, u32, u32) {
+        let ghosts = sim.ghosts.iter().map(|ghost| ghost.pos).collect();
+        (sim.player.pos, ghosts, sim.game_state.score, sim.game_state.lives)
+    }
+
+    #[test]
+    fn seeking_ends_where_playing_through_does() {
+        let mut sim = Simulation::new(default_ghost_config(), None);
+        let config = SimConfig { seed: 7, ..SimConfig::new() };
+        sim.start_game(config);
+        let mut replay = Replay::new(&sim);
+        for tick in 0..3000 {
+            let input = PlayerInput {
+                forward: 1.0,
+                turn: if (tick / 80) % 4 == 0 { -0.6 } else { 0.0 },
+                ..PlayerInput::default()
+            };
+            sim.tick(&input);
+            replay.record(&input);
+        }
+        let recorded = snapshot(&sim);
+
+        let mut straight = Playback::new(replay.clone());
+        straight.restart(&mut sim);
+        while !straight.is_finished() {
+            straight.step(&mut sim);
+        }
+        assert_eq!(snapshot(&sim), recorded);
+
+        let mut seeking = Playback::new(replay);
+        seeking.restart(&mut sim);
+        seeking.seek(&mut sim, 2000);
+        seeking.seek(&mut sim, 500);
+        assert_eq!(seeking.tick, 500);
+        seeking.seek(&mut sim, usize::MAX);
+        assert!(seeking.is_finished());
+        assert_eq!(snapshot(&sim), recorded);
+    }
+}
@@ -0,0 +1,77 @@
+use raylib::prelude::*;
+use pacman::scores::{ScoreEntry, NAME_LENGTH};
+use crate::input::{Action, InputMap};
+
+// Arcade-style initials: up/down changes the letter, left/right moves
+// between them, confirm saves
+pub struct NameEntry {
+    letters: [u8; NAME_LENGTH],
+    cursor: usize,
+}
+
+impl NameEntry {
+    pub fn new() -> Self {
+        NameEntry {
+            letters: [b'A'; NAME_LENGTH],
+            cursor: 0,
+        }
+    }
+
+    // Returns the name once it has been confirmed
+    pub fn update(&mut self, rl: &RaylibHandle, input: &InputMap) -> Option<String> {
+        let letter = &mut self.letters[self.cursor];
+        if input.is_pressed(rl, Action::MenuUp) {
+            *letter = if *letter == b'Z' { b'A' } else { *letter + 1 };
+        }
+        if input.is_pressed(rl, Action::MenuDown) {
+            *letter = if *letter == b'A' { b'Z' } else { *letter - 1 };
+        }
+        if input.is_pressed(rl, Action::MenuLeft) {
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+        if input.is_pressed(rl, Action::MenuRight) {
+            self.cursor = (self.cursor + 1).min(NAME_LENGTH - 1);
+        }
+        if input.is_pressed(rl, Action::Confirm) {
+            return Some(String::from_utf8_lossy(&self.letters).to_string());
+        }
+        None
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, x: i32, y: i32, input: &InputMap) {
+        d.draw_text("NEW HIGH SCORE! Enter your initials", x, y, 20, Color::GOLD);
+        for (i, &letter) in self.letters.iter().enumerate() {
+            let color = if i == self.cursor { Color::GREEN } else { Color::WHITE };
+            let letter_x = x + 120 + i as i32 * 40;
+            d.draw_text(&(letter as char).to_string(), letter_x, y + 35, 36, color);
+            if i == self.cursor {
+                d.draw_rectangle(letter_x, y + 75, 26, 4, color);
+            }
+        }
+        let help = format!(
+            "{}/{} letter, {}/{} move, {} to save",
+            input.describe(Action::MenuUp),
+            input.describe(Action::MenuDown),
+            input.describe(Action::MenuLeft),
+            input.describe(Action::MenuRight),
+            input.describe(Action::Confirm),
+        );
+        d.draw_text(&help, x, y + 95, 16, Color::LIGHTGRAY);
+    }
+}
+
+// The top ten, with `highlight` (a place on the table) in another colour
+pub fn draw_table(d: &mut RaylibDrawHandle, entries: &[ScoreEntry], highlight: Option<usize>, x: i32, y: i32) {
+    if entries.is_empty() {
+        d.draw_text("No scores yet", x, y, 20, Color::LIGHTGRAY);
+        return;
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let color = if highlight == Some(i) { Color::GREEN } else { Color::WHITE };
+        let row_y = y + i as i32 * 28;
+        d.draw_text(&format!("{:>2}.", i + 1), x, row_y, 22, color);
+        d.draw_text(&entry.name, x + 50, row_y, 22, color);
+        d.draw_text(&format!("{:06}", entry.score), x + 130, row_y, 22, color);
+        d.draw_text(&format!("LV {}", entry.level), x + 240, row_y, 22, color);
+    }
+}
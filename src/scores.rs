@@ -0,0 +1,131 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use crate::level::Difficulty;
+
+pub const HIGH_SCORES_FILE: &str = "highscores.txt";
+pub const TABLE_SIZE: usize = 10;
+pub const NAME_LENGTH: usize = 3;
+
+const DIFFICULTIES: [(Difficulty, &str); 3] = [
+    (Difficulty::Easy, "easy"),
+    (Difficulty::Medium, "medium"),
+    (Difficulty::Hard, "hard"),
+];
+
+#[derive(Debug, Clone)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u32,
+    pub level: u32,
+}
+
+// Top scores for every maze (by Maze::layout_id) and difficulty, kept in a
+// text file with one `maze difficulty NAME score level` line per entry
+pub struct HighScores {
+    tables: HashMap<(u32, Difficulty), Vec<ScoreEntry>>,
+}
+
+impl Default for HighScores {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HighScores {
+    pub fn new() -> Self {
+        HighScores { tables: HashMap::new() }
+    }
+
+    // A missing file is just an empty table; bad lines are reported and skipped
+    pub fn load(path: &str) -> Self {
+        let mut scores = HighScores::new();
+        let Ok(contents) = fs::read_to_string(path) else {
+            return scores;
+        };
+        for (line_no, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_line(line) {
+                Some((maze, difficulty, entry)) => {
+                    scores.tables.entry((maze, difficulty)).or_default().push(entry);
+                }
+                None => println!("{}:{}: línea ignorada '{}'", path, line_no + 1, line),
+            }
+        }
+        for table in scores.tables.values_mut() {
+            table.sort_by_key(|entry| Reverse(entry.score));
+            table.truncate(TABLE_SIZE);
+        }
+        scores
+    }
+
+    pub fn save(&self, path: &str) {
+        let mut keys: Vec<&(u32, Difficulty)> = self.tables.keys().collect();
+        keys.sort_by_key(|(maze, difficulty)| (*maze, difficulty_name(*difficulty)));
+        let mut contents = String::from("# Pac-Man 3D high scores: maze difficulty NAME score level\n");
+        for key in keys {
+            for entry in self.tables[key].iter() {
+                contents.push_str(&format!(
+                    "{:08x} {} {} {} {}\n",
+                    key.0,
+                    difficulty_name(key.1),
+                    entry.name,
+                    entry.score,
+                    entry.level
+                ));
+            }
+        }
+        if let Err(e) = fs::write(path, contents) {
+            println!("Error al guardar {}: {}", path, e);
+        }
+    }
+
+    pub fn table(&self, maze: u32, difficulty: Difficulty) -> &[ScoreEntry] {
+        self.tables.get(&(maze, difficulty)).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn best(&self, maze: u32, difficulty: Difficulty) -> u32 {
+        self.table(maze, difficulty).first().map_or(0, |entry| entry.score)
+    }
+
+    // Whether the score would make it onto the table
+    pub fn qualifies(&self, maze: u32, difficulty: Difficulty, score: u32) -> bool {
+        let table = self.table(maze, difficulty);
+        score > 0 && (table.len() < TABLE_SIZE || table.last().is_some_and(|last| score > last.score))
+    }
+
+    // Returns the entry's place on the table, None if it didn't make it.
+    // Ties go below the scores already there.
+    pub fn insert(&mut self, maze: u32, difficulty: Difficulty, entry: ScoreEntry) -> Option<usize> {
+        let table = self.tables.entry((maze, difficulty)).or_default();
+        let rank = table.iter().position(|other| entry.score > other.score).unwrap_or(table.len());
+        if rank >= TABLE_SIZE {
+            return None;
+        }
+        table.insert(rank, entry);
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+}
+
+fn difficulty_name(difficulty: Difficulty) -> &'static str {
+    DIFFICULTIES.iter().find(|(d, _)| *d == difficulty).map_or("medium", |(_, name)| name)
+}
+
+fn parse_line(line: &str) -> Option<(u32, Difficulty, ScoreEntry)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [maze, difficulty, name, score, level] = fields.as_slice() else {
+        return None;
+    };
+    let maze = u32::from_str_radix(maze, 16).ok()?;
+    let difficulty = DIFFICULTIES.iter().find(|(_, n)| n == difficulty)?.0;
+    let entry = ScoreEntry {
+        name: name.to_string(),
+        score: score.parse().ok()?,
+        level: level.parse().ok()?,
+    };
+    Some((maze, difficulty, entry))
+}
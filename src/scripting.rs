@@ -0,0 +1,266 @@
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::rc::Rc;
+use crate::brain::{ArcadeBrain, GhostBrain, GhostInfo, GhostView};
+use crate::ghost::GhostState;
+use crate::math::fnv1a;
+use crate::maze::{Cell, GridDir, Maze, HEIGHT, WIDTH};
+use crate::sim::SimRng;
+
+pub const SCRIPT_FILE: &str = "level.rhai";
+// `brain = script:NAME` in ghosts.cfg steers the ghost with the script's fn NAME
+pub const SCRIPT_BRAIN_PREFIX: &str = "script:";
+
+// A single runaway script can't freeze the game: every call gets this many
+// operations before it is aborted
+const MAX_OPERATIONS: u64 = 50_000;
+
+// Read-only copy of the maze handed to scripts. Cloning it for a call only
+// bumps the grid's reference count.
+#[derive(Clone)]
+pub struct MazeQuery {
+    grid: Rc<Vec<Vec<Cell>>>,
+    door: Option<(i32, i32)>,
+    pellets_remaining: u32,
+}
+
+impl MazeQuery {
+    fn new(maze: &Maze) -> Self {
+        MazeQuery {
+            grid: Rc::new(maze.grid.clone()),
+            door: maze.door(),
+            pellets_remaining: maze.pellets_remaining(),
+        }
+    }
+
+    fn cell(&self, x: i64, y: i64) -> Cell {
+        if x < 0 || y < 0 || x >= WIDTH as i64 || y >= HEIGHT as i64 {
+            return Cell::Wall;
+        }
+        self.grid[y as usize][x as usize]
+    }
+}
+
+// What a level event asks the game to do, read from the map a handler returns:
+// #{ message: "...", score: 500, frighten: true }
+#[derive(Default)]
+pub struct ScriptEffects {
+    pub message: Option<String>,
+    pub score: u32,
+    pub frighten: bool,
+}
+
+pub enum LevelEvent {
+    LevelStart(u32),
+    Pellet((i32, i32)),
+    PowerPellet((i32, i32)),
+    GhostEaten(u32), // ghosts eaten on this power pellet so far
+    Death(u32),      // lives left once this one is taken
+}
+
+// Ghost behaviours and level event handlers written in Rhai. Scripts can't
+// import modules or touch files; they only see what they are passed.
+pub struct Scripts {
+    engine: Engine,
+    ast: AST,
+    functions: HashSet<(String, usize)>,
+    // Hash of the script file, so replays can tell it has changed
+    pub source_hash: u32,
+}
+
+impl Scripts {
+    pub fn load(path: &str) -> Option<Rc<Scripts>> {
+        if !std::path::Path::new(path).exists() {
+            return None;
+        }
+        let engine = sandboxed_engine();
+        let source_hash = std::fs::read(path).map_or(0, fnv1a);
+        let ast = match engine.compile_file(PathBuf::from(path)) {
+            Ok(ast) => ast,
+            Err(e) => {
+                println!("Error en el script {}: {}", path, e);
+                return None;
+            }
+        };
+        let functions: HashSet<(String, usize)> = ast
+            .iter_functions()
+            .map(|f| (f.name.to_string(), f.params.len()))
+            .collect();
+        let mut names: Vec<&str> = functions.iter().map(|(name, _)| name.as_str()).collect();
+        names.sort();
+        println!("Script {} cargado: {}", path, names.join(", "));
+        Some(Rc::new(Scripts { engine, ast, functions, source_hash }))
+    }
+
+    fn has_fn(&self, name: &str, params: usize) -> bool {
+        self.functions.contains(&(name.to_string(), params))
+    }
+
+    // A brain for `script:NAME`, if the script defines NAME(maze, ghost, player, ghosts, options)
+    pub fn brain(self: &Rc<Self>, name: &str) -> Option<Box<dyn GhostBrain>> {
+        let function = name.strip_prefix(SCRIPT_BRAIN_PREFIX)?;
+        if !self.has_fn(function, 5) {
+            println!("{} no define {}(maze, ghost, player, ghosts, options)", SCRIPT_FILE, function);
+            return None;
+        }
+        Some(Box::new(ScriptBrain {
+            scripts: Rc::clone(self),
+            function: function.to_string(),
+            memory: Map::new().into(),
+            maze: None,
+            broken: false,
+        }))
+    }
+
+    // Runs the handler for an event if the script has one
+    pub fn fire(&self, event: LevelEvent) -> ScriptEffects {
+        let (name, args): (&str, Vec<Dynamic>) = match event {
+            LevelEvent::LevelStart(level) => ("on_level_start", vec![(level as i64).into()]),
+            LevelEvent::Pellet((x, y)) => ("on_pellet", vec![(x as i64).into(), (y as i64).into()]),
+            LevelEvent::PowerPellet((x, y)) => ("on_power_pellet", vec![(x as i64).into(), (y as i64).into()]),
+            LevelEvent::GhostEaten(count) => ("on_ghost_eaten", vec![(count as i64).into()]),
+            LevelEvent::Death(lives) => ("on_death", vec![(lives as i64).into()]),
+        };
+        if !self.has_fn(name, args.len()) {
+            return ScriptEffects::default();
+        }
+        let options = CallFnOptions::new().eval_ast(false);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, name, args);
+        match result {
+            Ok(value) => effects_from(value),
+            Err(e) => {
+                println!("Error en {}: {}", name, e);
+                ScriptEffects::default()
+            }
+        }
+    }
+}
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1024);
+    engine.set_max_array_size(1024);
+    engine.set_max_map_size(256);
+    engine.on_print(|text| println!("[script] {}", text));
+
+    engine
+        .register_type_with_name::<MazeQuery>("Maze")
+        .register_get("width", |_: &mut MazeQuery| WIDTH as i64)
+        .register_get("height", |_: &mut MazeQuery| HEIGHT as i64)
+        .register_get("pellets_remaining", |maze: &mut MazeQuery| maze.pellets_remaining as i64)
+        .register_fn("is_wall", |maze: &mut MazeQuery, x: i64, y: i64| maze.cell(x, y) == Cell::Wall)
+        .register_fn("is_door", |maze: &mut MazeQuery, x: i64, y: i64| maze.door == Some((x as i32, y as i32)))
+        .register_fn("has_pellet", |maze: &mut MazeQuery, x: i64, y: i64| {
+            matches!(maze.cell(x, y), Cell::Pellet | Cell::PowerPellet)
+        });
+    engine
+}
+
+fn effects_from(value: Dynamic) -> ScriptEffects {
+    let Some(map) = value.try_cast::<Map>() else {
+        return ScriptEffects::default();
+    };
+    ScriptEffects {
+        message: map.get("message").map(|message| message.to_string()),
+        score: map.get("score").and_then(|score| score.as_int().ok()).map_or(0, |score| score.max(0) as u32),
+        frighten: map.get("frighten").and_then(|frighten| frighten.as_bool().ok()).unwrap_or(false),
+    }
+}
+
+fn state_name(state: GhostState) -> &'static str {
+    match state {
+        GhostState::Scatter => "scatter",
+        GhostState::Chase => "chase",
+        GhostState::Frightened => "frightened",
+        GhostState::Eaten => "eaten",
+    }
+}
+
+fn dir_array(dir: GridDir) -> Dynamic {
+    let array: Array = vec![(dir.0 as i64).into(), (dir.1 as i64).into()];
+    array.into()
+}
+
+fn ghost_map(ghost: &GhostInfo) -> Map {
+    let mut map = Map::new();
+    map.insert("x".into(), (ghost.tile.0 as i64).into());
+    map.insert("y".into(), (ghost.tile.1 as i64).into());
+    map.insert("state".into(), state_name(ghost.state).into());
+    map.insert("personality".into(), format!("{:?}", ghost.personality).to_lowercase().into());
+    map
+}
+
+// Calls a script function for every turn. The function gets its own `this`
+// map to remember things between calls, and must return one of `options`
+// as [dx, dy]; anything else falls back to the arcade rules.
+pub struct ScriptBrain {
+    scripts: Rc<Scripts>,
+    function: String,
+    memory: Dynamic,
+    // Copy of the maze from the last call. Eating a pellet is the only change a
+    // maze sees while its ghosts are alive, so it is rebuilt when the count moves.
+    maze: Option<MazeQuery>,
+    // Set after the first error so a broken script is reported once
+    broken: bool,
+}
+
+impl GhostBrain for ScriptBrain {
+    fn name(&self) -> &'static str {
+        "script"
+    }
+
+    fn choose_direction(&mut self, view: &GhostView, options: &[GridDir], rng: &mut SimRng) -> GridDir {
+        if self.broken {
+            return ArcadeBrain.choose_direction(view, options, rng);
+        }
+
+        let mut ghost = ghost_map(&view.me);
+        ghost.insert("dir".into(), dir_array(view.dir));
+        ghost.insert("target_x".into(), (view.target.0 as i64).into());
+        ghost.insert("target_y".into(), (view.target.1 as i64).into());
+        let mut player = Map::new();
+        player.insert("x".into(), (view.world.player_tile.0 as i64).into());
+        player.insert("y".into(), (view.world.player_tile.1 as i64).into());
+        player.insert("dir".into(), dir_array(view.world.player_dir));
+        let ghosts: Array = view.world.ghosts.iter().map(|other| ghost_map(other).into()).collect();
+        let choices: Array = options.iter().map(|&dir| dir_array(dir)).collect();
+        let maze = match &self.maze {
+            Some(maze) if maze.pellets_remaining == view.maze.pellets_remaining() => maze.clone(),
+            _ => self.maze.insert(MazeQuery::new(view.maze)).clone(),
+        };
+
+        let scripts = &self.scripts;
+        let options_call = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.memory);
+        let result = scripts.engine.call_fn_with_options::<Array>(
+            options_call,
+            &mut Scope::new(),
+            &scripts.ast,
+            &self.function,
+            (maze, ghost, player, ghosts, choices),
+        );
+        let chosen = match result {
+            Ok(dir) => match dir.as_slice() {
+                [dx, dy] => dx.as_int().ok().zip(dy.as_int().ok()).map(|(dx, dy)| (dx as i32, dy as i32)),
+                _ => None,
+            },
+            Err(e) => {
+                println!("Error en {}: {}; se usa el comportamiento arcade", self.function, e);
+                self.broken = true;
+                None
+            }
+        };
+        match chosen {
+            Some(dir) if options.contains(&dir) => dir,
+            _ => ArcadeBrain.choose_direction(view, options, rng),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+use crate::math::Vec2;
+use crate::replay::{Playback, Replay};
+use crate::sim::Simulation;
+
+// Time attack records sit next to the other data files, one per maze,
+// difficulty, number of ghosts and movement mode, so runs are only raced
+// against runs played under the same conditions. Records are always time
+// attack games, so the variant needn't be part of the name.
+pub fn best_run_path(sim: &Simulation) -> String {
+    format!(
+        "best_{:08x}_{}_{}_{}.replay",
+        sim.maze.layout_id(),
+        format!("{:?}", sim.config.difficulty).to_lowercase(),
+        sim.ghosts.len(),
+        format!("{:?}", sim.config.movement).to_lowercase()
+    )
+}
+
+// The player's path through a recorded run, for racing against it: entry n
+// is where the player was after n ticks
+pub struct Shadow {
+    pub replay: Replay,
+    track: Vec<Vec2>,
+}
+
+impl Shadow {
+    // Plays the run through once up front; `sim` only lends its ghosts and scripts
+    pub fn new(replay: Replay, sim: &Simulation) -> Self {
+        let mut sim = sim.fresh();
+        let mut playback = Playback::new(replay);
+        playback.restart(&mut sim);
+        let mut track = vec![sim.player.pos];
+        while !playback.is_finished() {
+            playback.step(&mut sim);
+            track.push(sim.player.pos);
+        }
+        Shadow { replay: playback.replay, track }
+    }
+
+    // The best run for the game `sim` is playing, if there is one
+    pub fn load_best(sim: &Simulation) -> Option<Shadow> {
+        let path = best_run_path(sim);
+        if !Path::new(&path).exists() {
+            return None;
+        }
+        let replay = Replay::load(&path)?;
+        replay.check_data(sim, &path);
+        Some(Shadow::new(replay, sim))
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.track.len() - 1
+    }
+
+    // Between the positions after `tick - 1` and `tick` ticks, like the
+    // player is drawn; None once the shadow has finished its run
+    pub fn position(&self, tick: usize, alpha: f32) -> Option<Vec2> {
+        let to = *self.track.get(tick)?;
+        let from = self.track[tick.saturating_sub(1)];
+        Some(from.lerp(to, alpha))
+    }
+}
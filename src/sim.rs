@@ -0,0 +1,483 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::rc::Rc;
+use crate::brain::{ArcadeBrain, brain_by_name};
+use crate::game_state::{GameMode, GameState};
+use crate::ghost::{Ghost, GhostState, HouseRelease, ModeSchedule, Personality, update_ghosts};
+use crate::level::{Difficulty, GameVariant, GhostConfig, LevelConfig, LevelTuning};
+use crate::maze::{Maze, CELL_SIZE, tile_centre};
+use crate::player::{Player, PlayerInput, MovementMode, update_player};
+use crate::scripting::{LevelEvent, ScriptEffects, Scripts};
+use crate::stealth::{Senses, PELLET_NOISE, POWER_PELLET_NOISE};
+
+// The simulation always advances in steps of SIM_DT, independent of the
+// render rate
+pub const SIM_HZ: f32 = 120.0;
+pub const SIM_DT: f32 = 1.0 / SIM_HZ;
+const PLAYER_SPAWN: (usize, usize) = (10, 15);
+
+// The one source of randomness in a game. Everything random draws from it,
+// so the same seed and the same inputs always play out the same way.
+pub type SimRng = StdRng;
+
+// Choices made before a game starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimConfig {
+    pub difficulty: Difficulty,
+    pub variant: GameVariant,
+    pub num_ghosts: usize,
+    pub movement: MovementMode,
+    pub seed: u64,
+    pub start_level: u32,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimConfig {
+    pub fn new() -> Self {
+        SimConfig {
+            difficulty: Difficulty::Medium,
+            variant: GameVariant::Classic,
+            num_ghosts: usize::MAX,
+            movement: MovementMode::Free,
+            seed: 0,
+            start_level: 1,
+        }
+    }
+}
+
+// Things that happened during a tick that the frontend may want to react to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimEvent {
+    PelletEaten,
+    PowerPelletEaten,
+    GhostEaten,
+    GhostsSpawned, // `ghosts` was rebuilt
+}
+
+// The whole game without a window: maze, player, ghosts, scoring and
+// timers, advanced one fixed tick at a time from a PlayerInput
+pub struct Simulation {
+    pub maze: Maze,
+    pub player: Player,
+    pub ghosts: Vec<Ghost>,
+    pub game_state: GameState,
+    pub config: SimConfig,
+    schedule: ModeSchedule,
+    house: HouseRelease,
+    ghost_config: GhostConfig,
+    scripts: Option<Rc<Scripts>>,
+    events: Vec<SimEvent>,
+    rng: SimRng,
+}
+
+impl Simulation {
+    pub fn new(ghost_config: GhostConfig, scripts: Option<Rc<Scripts>>) -> Self {
+        Simulation {
+            maze: Maze::new(),
+            player: Player::new(PLAYER_SPAWN.0, PLAYER_SPAWN.1, MovementMode::Free),
+            ghosts: Vec::new(),
+            game_state: GameState::new(),
+            config: SimConfig::new(),
+            schedule: ModeSchedule::new(Vec::new()),
+            house: HouseRelease::new(0.0),
+            ghost_config,
+            scripts,
+            events: Vec::new(),
+            rng: SimRng::seed_from_u64(0),
+        }
+    }
+
+    // A brand new simulation with the same ghosts and scripts
+    pub fn fresh(&self) -> Simulation {
+        Simulation::new(self.ghost_config.clone(), self.scripts.clone())
+    }
+
+    // Hashes of the ghosts file and the script this simulation was built
+    // from; a replay only plays back the same with the same data
+    pub fn data_hashes(&self) -> (u32, u32) {
+        let scripts = self.scripts.as_ref().map_or(0, |scripts| scripts.source_hash);
+        (self.ghost_config.source_hash, scripts)
+    }
+
+    pub fn max_ghosts(&self) -> usize {
+        LevelConfig::for_level(self.game_state.level, &self.ghost_config).max_ghosts()
+    }
+
+    pub fn start_game(&mut self, config: SimConfig) {
+        self.config = config;
+        self.rng = SimRng::seed_from_u64(config.seed);
+        self.game_state.reset_game();
+        self.game_state.level = config.start_level.max(1);
+        self.start_level();
+    }
+
+    pub fn next_level(&mut self) {
+        self.game_state.next_level();
+        self.start_level();
+    }
+
+    // Everything that happened since the last call
+    pub fn take_events(&mut self) -> Vec<SimEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // One SIM_DT step. Paused and menu time is the caller's business: it
+    // simply doesn't tick then. The input is quantized first, so a replay
+    // storing it compactly plays back exactly.
+    pub fn tick(&mut self, input: &PlayerInput) {
+        match self.game_state.mode {
+            GameMode::Playing => self.step(&input.quantized()),
+            GameMode::Ready | GameMode::Dying => {
+                self.game_state.update(SIM_DT);
+                if self.game_state.update_sequence(SIM_DT) {
+                    self.respawn();
+                }
+            }
+            _ => self.game_state.update(SIM_DT),
+        }
+    }
+
+    fn tuning(&self) -> (LevelConfig, LevelTuning) {
+        let level = self.game_state.level;
+        (
+            LevelConfig::for_level(level, &self.ghost_config),
+            LevelTuning::new(level, self.config.difficulty, self.config.variant),
+        )
+    }
+
+    fn start_level(&mut self) {
+        let (level, tuning) = self.tuning();
+        self.ghosts = self.spawn_ghosts(&level, &tuning);
+        self.schedule = ModeSchedule::new(tuning.schedule);
+        self.game_state.power_mode_duration = tuning.frightened_duration;
+        self.house = HouseRelease::new(level.house_timer);
+        self.maze = Maze::new();
+        self.player = Player::new(PLAYER_SPAWN.0, PLAYER_SPAWN.1, self.config.movement);
+        self.game_state.start_ready();
+        let effects = self.fire(LevelEvent::LevelStart(self.game_state.level));
+        self.apply_script_effects(effects);
+    }
+
+    // Back to the spawn points after a death; eaten pellets stay eaten
+    fn respawn(&mut self) {
+        let (level, tuning) = self.tuning();
+        self.ghosts = self.spawn_ghosts(&level, &tuning);
+        self.schedule = ModeSchedule::new(tuning.schedule);
+        self.house.life_lost();
+        self.player = Player::new(PLAYER_SPAWN.0, PLAYER_SPAWN.1, self.config.movement);
+    }
+
+    fn spawn_ghosts(&mut self, level: &LevelConfig, tuning: &LevelTuning) -> Vec<Ghost> {
+        let mut result = Vec::new();
+        for spec in level.ghosts.iter().take(self.config.num_ghosts) {
+            let brain = self
+                .scripts
+                .as_ref()
+                .and_then(|scripts| scripts.brain(&spec.brain))
+                .or_else(|| brain_by_name(&spec.brain))
+                .unwrap_or_else(|| Box::new(ArcadeBrain));
+            let pos = tile_centre(spec.spawn);
+            let mut ghost = Ghost::new(pos, tuning.ghost_speed, spec.model.clone(), spec.color, spec.personality, spec.release_delay, brain);
+            ghost.dot_limit = spec.dot_limit(level.level);
+            ghost.global_dot_limit = spec.global_dot_limit;
+            if spec.personality == Personality::Blinky {
+                ghost.elroy_dots = tuning.elroy_dots;
+            }
+            if tuning.stealth {
+                ghost.senses = Some(Senses::new());
+            }
+            result.push(ghost);
+        }
+        self.events.push(SimEvent::GhostsSpawned);
+        result
+    }
+
+    fn fire(&self, event: LevelEvent) -> ScriptEffects {
+        self.scripts.as_ref().map(|scripts| scripts.fire(event)).unwrap_or_default()
+    }
+
+    fn apply_script_effects(&mut self, effects: ScriptEffects) {
+        if let Some(message) = effects.message {
+            self.game_state.show_message(message);
+        }
+        if effects.score > 0 {
+            self.game_state.add_score(effects.score);
+        }
+        if effects.frighten {
+            self.game_state.activate_power_mode();
+            for ghost in self.ghosts.iter_mut() {
+                ghost.frighten();
+            }
+        }
+    }
+
+    fn step(&mut self, cmd: &PlayerInput) {
+        let dt = SIM_DT;
+        self.player.store_previous();
+        for ghost in self.ghosts.iter_mut() {
+            ghost.prev_pos = ghost.pos;
+        }
+
+        self.game_state.update(dt);
+        update_player(&mut self.player, cmd, &self.maze, &self.game_state, dt);
+
+        let grid_x = (self.player.pos.x / CELL_SIZE as f32) as usize;
+        let grid_y = (self.player.pos.y / CELL_SIZE as f32) as usize;
+
+        if let Some(points) = self.maze.collect_pellet(grid_x, grid_y) {
+            self.game_state.add_score(points);
+            self.house.pellet_eaten(&mut self.ghosts);
+
+            let tile = (grid_x as i32, grid_y as i32);
+            let noise = if points == 50 { POWER_PELLET_NOISE } else { PELLET_NOISE };
+            for ghost in self.ghosts.iter_mut() {
+                ghost.hear(tile, noise);
+            }
+            let event = if points == 50 {
+                self.events.push(SimEvent::PowerPelletEaten);
+                self.game_state.activate_power_mode();
+                for ghost in self.ghosts.iter_mut() {
+                    ghost.frighten();
+                }
+                LevelEvent::PowerPellet(tile)
+            } else {
+                self.events.push(SimEvent::PelletEaten);
+                LevelEvent::Pellet(tile)
+            };
+            let effects = self.fire(event);
+            self.apply_script_effects(effects);
+        }
+
+        // A cleared maze ends the tick: no ghost can still catch the player
+        if self.maze.is_level_complete() {
+            self.game_state.complete_level();
+            return;
+        }
+
+        update_ghosts(
+            &mut self.ghosts,
+            &mut self.schedule,
+            &mut self.house,
+            &self.maze,
+            &self.player,
+            self.game_state.power_mode_active,
+            dt,
+            &mut self.rng,
+        );
+        let ghosts_eaten_before = self.game_state.ghosts_eaten;
+        for ghost in self.ghosts.iter_mut() {
+            let touching = (ghost.pos.x - self.player.pos.x).abs() < CELL_SIZE as f32 / 2.0
+                && (ghost.pos.y - self.player.pos.y).abs() < CELL_SIZE as f32 / 2.0;
+            if !touching {
+                continue;
+            }
+            match ghost.state {
+                GhostState::Frightened => {
+                    ghost.state = GhostState::Eaten;
+                    self.game_state.eat_ghost(ghost.pos);
+                    self.events.push(SimEvent::GhostEaten);
+                }
+                GhostState::Eaten => {}
+                _ => {
+                    self.game_state.start_death();
+                    let effects = self.fire(LevelEvent::Death(self.game_state.lives.saturating_sub(1)));
+                    self.apply_script_effects(effects);
+                    return;
+                }
+            }
+        }
+        if self.game_state.ghosts_eaten > ghosts_eaten_before {
+            let effects = self.fire(LevelEvent::GhostEaten(self.game_state.ghosts_eaten));
+            self.apply_script_effects(effects);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::default_ghost_config;
+    use crate::math::Vec2;
+    use crate::maze::{Cell, HEIGHT, WIDTH};
+
+    const PELLET: (i32, i32) = (1, 1);
+    const POWER_PELLET: (i32, i32) = (1, 2);
+
+    // A game past its READY countdown, with up to `num_ghosts` of the
+    // classic four (level 1 has three)
+    fn playing(num_ghosts: usize) -> Simulation {
+        start(SimConfig { num_ghosts, ..SimConfig::new() })
+    }
+
+    fn start(config: SimConfig) -> Simulation {
+        let mut sim = Simulation::new(default_ghost_config(), None);
+        sim.start_game(config);
+        while sim.game_state.mode == GameMode::Ready {
+            sim.tick(&PlayerInput::default());
+        }
+        sim
+    }
+
+    // Moves the player onto `tile` and runs one tick standing still
+    fn step_onto(sim: &mut Simulation, tile: (i32, i32)) {
+        sim.player.pos = tile_centre(tile);
+        sim.tick(&PlayerInput::default());
+    }
+
+    fn run_sequence(sim: &mut Simulation) {
+        while matches!(sim.game_state.mode, GameMode::Dying | GameMode::Ready) {
+            sim.tick(&PlayerInput::default());
+        }
+    }
+
+    // A ghost that isn't frightened catches the player
+    fn catch_player(sim: &mut Simulation) {
+        sim.ghosts[0].state = GhostState::Chase;
+        sim.ghosts[0].pos = sim.player.pos;
+        sim.tick(&PlayerInput::default());
+    }
+
+    #[test]
+    fn pellets_score_with_the_bonus_multiplier() {
+        let mut sim = playing(0);
+        let score = sim.game_state.score;
+        let multiplier = sim.game_state.bonus_multiplier;
+        step_onto(&mut sim, PELLET);
+        assert_eq!(sim.game_state.score, score + 10 * multiplier);
+        assert_eq!(sim.maze.get_cell(1, 1), Cell::Path);
+        assert!(sim.take_events().contains(&SimEvent::PelletEaten));
+
+        let score = sim.game_state.score;
+        let multiplier = sim.game_state.bonus_multiplier;
+        step_onto(&mut sim, POWER_PELLET);
+        assert_eq!(sim.game_state.score, score + 50 * multiplier);
+        assert!(sim.game_state.power_mode_active);
+        assert!(sim.take_events().contains(&SimEvent::PowerPelletEaten));
+    }
+
+    #[test]
+    fn power_pellet_frightens_the_ghosts() {
+        let mut sim = playing(4);
+        step_onto(&mut sim, POWER_PELLET);
+        assert!(sim.ghosts.iter().all(|ghost| ghost.state == GhostState::Frightened));
+    }
+
+    #[test]
+    fn ghost_chain_scores_200_to_1600() {
+        let mut sim = start(SimConfig { start_level: 2, ..SimConfig::new() });
+        assert_eq!(sim.ghosts.len(), 4);
+        step_onto(&mut sim, POWER_PELLET);
+        for (i, points) in [200, 400, 800, 1600].into_iter().enumerate() {
+            let score = sim.game_state.score;
+            sim.ghosts[i].state = GhostState::Frightened;
+            sim.ghosts[i].pos = sim.player.pos;
+            sim.tick(&PlayerInput::default());
+            assert_eq!(sim.ghosts[i].state, GhostState::Eaten);
+            assert_eq!(sim.game_state.score, score + points);
+            assert_eq!(sim.game_state.popups.last().map(|popup| popup.points), Some(points));
+        }
+    }
+
+    #[test]
+    fn death_respawns_and_keeps_eaten_pellets() {
+        let mut sim = playing(4);
+        step_onto(&mut sim, PELLET);
+        let remaining = sim.maze.pellets_remaining();
+
+        catch_player(&mut sim);
+        assert_eq!(sim.game_state.mode, GameMode::Dying);
+        run_sequence(&mut sim);
+
+        assert_eq!(sim.game_state.mode, GameMode::Playing);
+        assert_eq!(sim.game_state.lives, 2);
+        assert_eq!(sim.maze.pellets_remaining(), remaining);
+        assert_eq!(sim.maze.get_cell(1, 1), Cell::Path);
+        assert_eq!(sim.player.pos, tile_centre((PLAYER_SPAWN.0 as i32, PLAYER_SPAWN.1 as i32)));
+    }
+
+    #[test]
+    fn losing_the_last_life_ends_the_game() {
+        let mut sim = playing(4);
+        sim.game_state.lives = 1;
+        catch_player(&mut sim);
+        run_sequence(&mut sim);
+        assert_eq!(sim.game_state.mode, GameMode::GameOver);
+        assert_eq!(sim.game_state.lives, 0);
+    }
+
+    #[test]
+    fn eating_the_last_pellet_completes_the_level() {
+        let mut sim = playing(4);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                if (x as i32, y as i32) != PELLET {
+                    sim.maze.collect_pellet(x, y);
+                }
+            }
+        }
+        let score = sim.game_state.score;
+        // A ghost on the last pellet can't take the cleared level away
+        sim.ghosts[0].state = GhostState::Chase;
+        sim.ghosts[0].pos = tile_centre(PELLET);
+        step_onto(&mut sim, PELLET);
+
+        assert_eq!(sim.game_state.mode, GameMode::LevelComplete);
+        assert_eq!(sim.game_state.lives, 3);
+        assert!(sim.game_state.score >= score + 1000);
+
+        sim.next_level();
+        assert_eq!(sim.game_state.level, 2);
+        assert_eq!(sim.game_state.mode, GameMode::Ready);
+        assert!(!sim.maze.is_level_complete());
+    }
+
+    // Player, ghost positions and score after every tick of a scripted game
+    // that eats a power pellet along the way, so frightened ghosts roll the
+    // dice too
+    fn scripted_run(config: SimConfig, ticks: u32) -> Vec<(Vec2, Vec<Vec2>, u32)> {
+        let mut sim = Simulation::new(default_ghost_config(), None);
+        sim.start_game(config);
+        let mut frightened = false;
+        let mut trace = Vec::new();
+        for tick in 0..ticks {
+            if tick == 400 {
+                sim.player.pos = tile_centre(POWER_PELLET);
+            }
+            let input = PlayerInput {
+                forward: 1.0,
+                turn: if (tick / 90) % 3 == 0 { 0.7 } else { 0.0 },
+                ..PlayerInput::default()
+            };
+            sim.tick(&input);
+            frightened |= sim.ghosts.iter().any(|ghost| ghost.state == GhostState::Frightened);
+            trace.push((
+                sim.player.pos,
+                sim.ghosts.iter().map(|ghost| ghost.pos).collect(),
+                sim.game_state.score,
+            ));
+        }
+        assert!(frightened);
+        trace
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_out_the_same() {
+        for variant in [GameVariant::Classic, GameVariant::Stealth] {
+            let config = SimConfig { variant, seed: 42, ..SimConfig::new() };
+            assert_eq!(scripted_run(config, 2000), scripted_run(config, 2000));
+        }
+    }
+
+    #[test]
+    fn different_seeds_play_out_differently() {
+        let config = SimConfig { variant: GameVariant::Stealth, ..SimConfig::new() };
+        let first = scripted_run(SimConfig { seed: 1, ..config }, 2000);
+        let second = scripted_run(SimConfig { seed: 2, ..config }, 2000);
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,88 @@
+use std::rc::Rc;
+use crate::autopilot::Autopilot;
+use crate::game_state::{GameMode, GameState};
+use crate::level::GhostConfig;
+use crate::replay::{DIFFICULTIES, MOVEMENTS, VARIANTS};
+use crate::scripting::Scripts;
+use crate::sim::{SimConfig, Simulation, SIM_HZ};
+
+// A soak game that runs longer than this many ticks (an hour) is cut short
+const SOAK_MAX_TICKS: u32 = 3600 * SIM_HZ as u32;
+
+// Plays bot games back to back without a window, as fast as the simulation
+// runs, and reports any game that panics. Game n is played with seed
+// `first_seed + n - 1`, so a failure can be rerun on its own; the seed also
+// picks the variant, difficulty and movement mode. Returns false if one
+// panicked.
+pub fn run_soak(ghost_config: &GhostConfig, scripts: Option<&Rc<Scripts>>, games: u32, first_seed: u64) -> bool {
+    let mut panics = 0;
+    let mut total_score: u64 = 0;
+    let mut best_level = 0;
+    for game in 1..=games {
+        let seed = first_seed.wrapping_add(game as u64 - 1);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            soak_game(ghost_config, scripts, seed)
+        }));
+        match result {
+            Ok(game_state) => {
+                total_score += game_state.score as u64;
+                best_level = best_level.max(game_state.level);
+            }
+            Err(_) => {
+                panics += 1;
+                println!("Soak: la partida {} (semilla {}) terminó con un pánico", game, seed);
+            }
+        }
+        if game % 100 == 0 {
+            println!("Soak: {}/{} partidas", game, games);
+        }
+    }
+    println!(
+        "Soak: {} partidas, {} pánicos, puntuación media {}, nivel máximo {}",
+        games,
+        panics,
+        total_score / games.max(1) as u64,
+        best_level
+    );
+    panics == 0
+}
+
+// Consecutive seeds go through every combination of settings
+fn soak_config(seed: u64) -> SimConfig {
+    SimConfig {
+        variant: VARIANTS[(seed % 3) as usize],
+        difficulty: DIFFICULTIES[(seed / 3 % 3) as usize],
+        movement: MOVEMENTS[(seed / 9 % 2) as usize],
+        seed,
+        ..SimConfig::new()
+    }
+}
+
+pub fn soak_game(ghost_config: &GhostConfig, scripts: Option<&Rc<Scripts>>, seed: u64) -> GameState {
+    let mut sim = Simulation::new(ghost_config.clone(), scripts.cloned());
+    sim.start_game(soak_config(seed));
+    let mut bot = Autopilot::new();
+    for _ in 0..SOAK_MAX_TICKS {
+        match sim.game_state.mode {
+            GameMode::GameOver => break,
+            GameMode::LevelComplete => sim.next_level(),
+            _ => {}
+        }
+        let cmd = bot.drive(&sim.player, &sim.maze, &sim.ghosts);
+        sim.tick(&cmd);
+        sim.take_events();
+    }
+    sim.game_state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::default_ghost_config;
+
+    #[test]
+    fn the_bot_clears_a_level() {
+        let config = default_ghost_config();
+        assert!((0..4).any(|seed| soak_game(&config, None, seed).level >= 2));
+    }
+}
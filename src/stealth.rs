@@ -0,0 +1,120 @@
+use rand::seq::SliceRandom;
+use crate::math::Vec2;
+use crate::maze::{Cell, GridDir, Maze, CELL_SIZE, HEIGHT, WIDTH};
+use crate::ray::cast_ray;
+use crate::sim::SimRng;
+
+// How far a ghost sees down a corridor, in pixels
+const VIEW_DISTANCE: f32 = 8.0 * CELL_SIZE as f32;
+// Half the angle of the cone in front of a ghost it can see in
+const VIEW_HALF_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+// Closer than this a ghost notices the player even from behind
+const TOUCH_DISTANCE: f32 = 1.5 * CELL_SIZE as f32;
+// Seconds a ghost keeps hunting a last known position before giving up
+const MEMORY_TIME: f32 = 6.0;
+// Tiles away eating can be heard from
+pub const PELLET_NOISE: i32 = 4;
+pub const POWER_PELLET_NOISE: i32 = 10;
+
+// What a ghost knows about the player in stealth mode. Ghosts only hunt
+// where they last saw or heard the player, and patrol the remaining pellets
+// the rest of the time.
+pub struct Senses {
+    pub last_known: Option<(i32, i32)>,
+    memory: f32,
+    patrol: Option<(i32, i32)>,
+}
+
+impl Default for Senses {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Senses {
+    pub fn new() -> Self {
+        Senses {
+            last_known: None,
+            memory: 0.0,
+            patrol: None,
+        }
+    }
+
+    pub fn is_hunting(&self) -> bool {
+        self.last_known.is_some()
+    }
+
+    pub fn hear(&mut self, own_tile: (i32, i32), noise_tile: (i32, i32), radius: i32) {
+        let dx = noise_tile.0 - own_tile.0;
+        let dy = noise_tile.1 - own_tile.1;
+        if dx * dx + dy * dy <= radius * radius {
+            self.alert(noise_tile);
+        }
+    }
+
+    pub fn look(&mut self, pos: Vec2, facing: GridDir, player_pos: Vec2, maze: &Maze, dt: f32) {
+        if can_see(pos, facing, player_pos, maze) {
+            self.alert(((player_pos.x / CELL_SIZE as f32) as i32, (player_pos.y / CELL_SIZE as f32) as i32));
+            return;
+        }
+        self.memory -= dt;
+        if self.memory <= 0.0 {
+            self.last_known = None;
+        }
+    }
+
+    fn alert(&mut self, tile: (i32, i32)) {
+        self.last_known = Some(tile);
+        self.memory = MEMORY_TIME;
+    }
+
+    // Where to head: the last known position while hunting, otherwise a
+    // pellet picked at random, replaced once reached or eaten
+    pub fn target(&mut self, own_tile: (i32, i32), maze: &Maze, rng: &mut SimRng) -> (i32, i32) {
+        if self.last_known == Some(own_tile) {
+            // Got there and found nothing
+            self.last_known = None;
+        }
+        if let Some(tile) = self.last_known {
+            return tile;
+        }
+        let patrol_done = self.patrol.is_none_or(|tile| {
+            tile == own_tile || maze.get_cell(tile.0 as usize, tile.1 as usize) == Cell::Path
+        });
+        if patrol_done {
+            self.patrol = random_pellet(maze, rng);
+        }
+        self.patrol.unwrap_or(own_tile)
+    }
+}
+
+fn random_pellet(maze: &Maze, rng: &mut SimRng) -> Option<(i32, i32)> {
+    let pellets: Vec<(i32, i32)> = (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .filter(|&(x, y)| matches!(maze.get_cell(x, y), Cell::Pellet | Cell::PowerPellet))
+        .map(|(x, y)| (x as i32, y as i32))
+        .collect();
+    pellets.choose(rng).copied()
+}
+
+// Whether a ghost at `from` looking along `facing` sees `to`: close enough,
+// inside its view cone, and with no wall in between
+pub fn can_see(from: Vec2, facing: GridDir, to: Vec2, maze: &Maze) -> bool {
+    let offset = to - from;
+    let distance = offset.length();
+    if distance <= TOUCH_DISTANCE {
+        return true;
+    }
+    if distance > VIEW_DISTANCE {
+        return false;
+    }
+    if facing != (0, 0) {
+        let facing_angle = (facing.1 as f32).atan2(facing.0 as f32);
+        let mut off_axis = offset.y.atan2(offset.x) - facing_angle;
+        off_axis = (off_axis + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        if off_axis.abs() > VIEW_HALF_ANGLE {
+            return false;
+        }
+    }
+    cast_ray(from, offset.y.atan2(offset.x), maze).distance >= distance
+}
@@ -1,12 +1,14 @@
 use raylib::prelude::*;
 use std::collections::HashMap;
+use std::rc::Rc;
+use crate::assets::AssetCache;
 
 pub struct TextureManager {
-    pub textures: HashMap<u8, Texture2D>,
+    pub textures: HashMap<u8, Rc<Texture2D>>,
 }
 
 impl TextureManager {
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, assets: &mut AssetCache) -> Self {
         let mut textures = HashMap::new();
 
         // Lista de texturas a cargar
@@ -15,32 +17,10 @@ impl TextureManager {
             (2, "assets/moneda.gif"), // Points asset
         ];
 
+        // Errors end up in the asset cache's load report
         for (id, path) in texture_files {
-            if !std::path::Path::new(path).exists() {
-                println!("Error: No se encuentra el archivo {}", path);
-                continue;
-            }
-
-            match Image::load_image(path) {
-                Ok(image) => {
-                    println!(
-                        "Textura {} cargada: {}x{} píxeles",
-                        id, image.width, image.height
-                    );
-
-                    match rl.load_texture_from_image(&thread, &image) {
-                        Ok(texture) => {
-                            textures.insert(id, texture);
-                            println!("Textura {} convertida a Texture2D correctamente", id);
-                        }
-                        Err(e) => {
-                            println!("Error al crear textura desde imagen {}: {:?}", path, e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    println!("Error al cargar imagen {}: {:?}", path, e);
-                }
+            if let Some(texture) = assets.texture(rl, thread, path) {
+                textures.insert(id, texture);
             }
         }
 
@@ -51,14 +31,8 @@ impl TextureManager {
         TextureManager { textures }
     }
 
-    pub fn get_pixel_color(&self, texture_id: u8, _tx: u32, _ty: u32) -> Color {
-        // Fallback color only, as we cannot safely access image data
-        match texture_id {
-            1 => Color::RED,
-            2 => Color::BLUE,
-            3 => Color::GREEN,
-            _ => Color::WHITE,
-        }
+    pub fn get(&self, texture_id: u8) -> Option<&Texture2D> {
+        self.textures.get(&texture_id).map(Rc::as_ref)
     }
 
     pub fn get_wall_texture_id(&self, _wall_x: usize, _wall_y: usize) -> u8 {